@@ -1,25 +1,33 @@
-use crate::game::Game;
+use std::{mem, time::Instant};
+
+use crate::{
+    game::{Game, GameResult, Outcome},
+    game_record::{GameRecord, MoveRecord},
+    notation,
+};
 
 pub struct Arena<'a, F, F2, G, D>
 where
-    F: FnMut(&Vec<Vec<i8>>) -> usize,
-    F2: FnMut(&Vec<Vec<i8>>) -> usize,
+    F: FnMut(&G::State) -> G::Action,
+    F2: FnMut(&G::State) -> G::Action,
     G: Game,
-    D: Fn(&Vec<Vec<i8>>),
+    D: Fn(&G::State),
 {
     player1: F,
     player2: F2,
     game: &'a G,
     display: D,
+    names: [String; 2],
+    records: Vec<GameRecord<G::State>>,
 }
 
 /// An Arena class where any 2 agents can be pit against each other.
 impl<'a, F, F2, G, D> Arena<'a, F, F2, G, D>
 where
-    F: FnMut(&Vec<Vec<i8>>) -> usize,
-    F2: FnMut(&Vec<Vec<i8>>) -> usize,
+    F: FnMut(&G::State) -> G::Action,
+    F2: FnMut(&G::State) -> G::Action,
     G: Game,
-    D: Fn(&Vec<Vec<i8>>),
+    D: Fn(&G::State),
 {
     /// Input:
     ///     player 1,2: two functions that takes board as input, return action
@@ -36,56 +44,105 @@ where
             player2,
             game,
             display,
+            names: ["player1".to_owned(), "player2".to_owned()],
+            records: Vec::new(),
         }
     }
 
-    /// Executes one episode of a game.
+    /// Names player 1 and 2 in the game records.
+    pub fn with_names(mut self, player1: &str, player2: &str) -> Self {
+        self.names = [player1.to_owned(), player2.to_owned()];
+        self
+    }
+
+    /// Returns the records of the games played so far, and forgets them.
+    pub fn take_records(&mut self) -> Vec<GameRecord<G::State>> {
+        mem::take(&mut self.records)
+    }
+
+    /// Executes one episode of a game from start_board, in which player 1 is
+    /// to move. When start_player is -1 the colors of start_board are swapped,
+    /// so that both players can play the same opening.
+    ///
+    /// A player who plays an illegal action forfeits the game.
     ///
     /// Returns:
-    /// either
-    ///     winner: player who won the game (1 if player1, -1 if player2)
-    /// or
-    ///     draw result returned from the game that is neither 1, -1, nor 0.
-    pub fn play_game(&mut self, start_player: i8, verbose: bool) -> i8 {
+    ///     result: the result of the game for player1
+    pub fn play_game(
+        &mut self,
+        start_board: &G::State,
+        start_player: i8,
+        verbose: bool,
+    ) -> GameResult {
         let mut cur_player = start_player;
-        let mut board = self.game.get_init_board().clone();
+        let mut board = self.game.get_canonical_form(start_board, start_player);
         let mut it = 0;
-        while self.game.get_game_ended(&board, cur_player) == 0 {
+        let mut actions = Vec::new();
+        let mut record =
+            GameRecord::new(board.clone(), cur_player).with_players(&self.names[0], &self.names[1]);
+        let result = loop {
+            if let Some(result) = self.game.get_game_ended(&board, cur_player) {
+                break result;
+            }
             it += 1;
             if verbose {
                 println!("Turn {:?} Player {:?}", it, cur_player);
                 (self.display)(&board);
             }
             let canon = self.game.get_canonical_form(&board, cur_player);
+            let now = Instant::now();
             let action = if cur_player == 1 {
                 (self.player1)(&canon)
             } else {
                 (self.player2)(&canon)
             };
+            let time_ms = now.elapsed().as_secs_f32() * 1000.;
 
-            let valids = self.game.get_valid_moves(&canon, 1);
-
-            if valids[action] == 0 {
-                println!("Action {action} is not valid");
-                println!("valids = {:?}", valids);
-                assert!(valids[action] > 0);
+            // an illegal action loses the game instead of ending the run
+            let (next_board, next_player) =
+                match self.game.try_next_state(&board, cur_player, action) {
+                    Ok(next_state) => next_state,
+                    Err(e) => {
+                        println!("Player {cur_player} forfeits on turn {it}: {e}");
+                        break GameResult::forfeit();
+                    }
+                };
+            if verbose {
+                println!(
+                    "Player {:?} plays {}",
+                    cur_player,
+                    self.game.action_to_string(action)
+                );
             }
-            let next_state = self.game.get_next_state(&board, cur_player, action as u8);
-            board = next_state.0;
-            cur_player = next_state.1;
-        }
+            actions.push(action);
+            record
+                .moves
+                .push(MoveRecord::new(self.game.action_to_string(action)).with_time_ms(time_ms));
+            board = next_board;
+            cur_player = next_player;
+        };
+        // cur_player is 1 when it is player1's turn
+        let result = if cur_player == 1 {
+            result
+        } else {
+            result.for_opponent()
+        };
         if verbose {
             println!(
-                "Game over: Turn {it} Result {:?}",
-                self.game.get_game_ended(&board, 1)
+                "Game over: Turn {it} Result {:?} by {}",
+                result.outcome, result.margin
             );
             (self.display)(&board);
+            println!("Transcript: {}", notation::transcript(self.game, &actions));
         }
-        return cur_player * self.game.get_game_ended(&board, cur_player);
+        record.result = Some(result);
+        self.records.push(record);
+        result
     }
 
     /// Plays num games in which player1 starts num/2 games and player2 starts
-    /// num/2 games.
+    /// num/2 games. Every start board from the game is played twice, once
+    /// started by each player.
     ///
     /// Returns:
     ///     oneWon: games won by player1
@@ -96,26 +153,27 @@ where
         let mut one_won = 0;
         let mut two_won = 0;
         let mut draws = 0;
+        let mut forfeits = 0;
         for _ in 0..num {
-            let game_result = self.play_game(1, verbose);
-            if game_result == 1 {
-                one_won += 1;
-            } else if game_result == -1 {
-                two_won += 1;
-            } else {
-                draws += 1;
+            let start_board = self.game.get_start_board();
+            for start_player in [1, -1] {
+                let result = self.play_game(&start_board, start_player, verbose);
+                if result.forfeit.is_some() {
+                    forfeits += 1;
+                }
+                match result.outcome {
+                    Outcome::Win => one_won += 1,
+                    Outcome::Loss => two_won += 1,
+                    Outcome::Draw => draws += 1,
+                }
             }
         }
-        for _ in 0..num {
-            let game_result = self.play_game(-1, verbose);
-            if game_result == -1 {
-                one_won += 1;
-            } else if game_result == 1 {
-                two_won += 1;
-            } else {
-                draws += 1;
-            }
+        if forfeits > 0 {
+            println!("{forfeits} games were forfeited by an illegal action");
         }
         return (one_won, two_won, draws);
     }
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,36 @@
+use super::Arena;
+use crate::{
+    game::{Forfeit, Game, GameResult, Outcome},
+    mnk_game::MnkGame,
+};
+
+#[test]
+fn illegal_action_forfeits() {
+    let game = MnkGame::new(3);
+    // both players repeat their first move, so whoever moves second first
+    // plays an occupied square
+    let mut arena = Arena::new(
+        |_: &Vec<Vec<i8>>| 0,
+        |_: &Vec<Vec<i8>>| 1,
+        &game,
+        |_: &Vec<Vec<i8>>| {},
+    );
+    assert_eq!(
+        arena.play_game(game.get_init_board(), 1, false),
+        GameResult::forfeit()
+    );
+    let result = arena.play_game(game.get_init_board(), -1, false);
+    assert_eq!(result.outcome, Outcome::Win);
+    assert_eq!(result.forfeit, Some(Forfeit::IllegalAction));
+    assert_eq!(arena.play_games(4, false), (2, 2, 0));
+}
+
+#[test]
+fn legal_games_are_not_forfeited() {
+    let game = MnkGame::new(3);
+    let first_empty = |b: &Vec<Vec<i8>>| b.iter().flatten().position(|s| *s == 0).unwrap();
+    let mut arena = Arena::new(first_empty, first_empty, &game, |_: &Vec<Vec<i8>>| {});
+    // X plays a1, c1, b2 and a3, completing the diagonal from a3 to c1
+    let result = arena.play_game(game.get_init_board(), 1, false);
+    assert_eq!(result, GameResult::from_margin(1));
+}
@@ -1,9 +1,74 @@
-use std::{collections::HashSet, vec};
+use std::fmt;
 
-#[derive(Clone)]
+use serde::{Deserialize, Serialize};
+
+/// Record of a move made with `Board::execute_move`, enough to take it back
+/// with `Board::undo_move`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MoveUndo {
+    pub color: i8,
+    /// The square the disc was placed on, 0 if no empty square was filled.
+    pub placed: u64,
+    /// The discs that changed from -color to color.
+    pub flipped: u64,
+}
+
+impl MoveUndo {
+    pub fn pass(color: i8) -> Self {
+        MoveUndo {
+            color,
+            placed: 0,
+            flipped: 0,
+        }
+    }
+}
+
+/// Why pieces are not a board.
+#[derive(Debug, PartialEq, Eq)]
+pub enum PiecesError {
+    Empty,
+    /// The rows do not all have the same length.
+    RaggedRows,
+    /// A board with fewer than 2 rows or columns, or more than 64 squares.
+    InvalidSize {
+        rows: usize,
+        cols: usize,
+    },
+    /// A square that is not 1, -1 or 0.
+    InvalidSquare(i8),
+}
+
+impl fmt::Display for PiecesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PiecesError::Empty => write!(f, "the board has no squares"),
+            PiecesError::RaggedRows => write!(f, "the rows of the board have different lengths"),
+            PiecesError::InvalidSize { rows, cols } => write!(
+                f,
+                "{rows}x{cols} is not a board size: bitboards support 2x2 to 64 squares"
+            ),
+            PiecesError::InvalidSquare(square) => write!(f, "{square} is not a square"),
+        }
+    }
+}
+
+/// Bitboard representation of an Othello board of up to 64 squares, with
+/// `rows` rows of `cols` squares.
+///
+/// Square (x, y) maps to bit `x * cols + y`. `black` holds the discs of
+/// player 1 and `white` the discs of player -1.
+///
+/// Boards are serialized as their pieces, a list of rows of 1, -1 and 0.
+/// Deserializing other pieces fails with a PiecesError.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(into = "Vec<Vec<i8>>", try_from = "Vec<Vec<i8>>")]
 pub struct Board {
-    n: usize,
-    pub pieces: Vec<Vec<i8>>,
+    rows: usize,
+    cols: usize,
+    black: u64,
+    white: u64,
+    full: u64,
+    shifts: [(i8, u64); 8],
 }
 
 impl Board {
@@ -18,150 +83,259 @@ impl Board {
         (0, 1),
     ];
 
-    pub fn new(n: usize) -> Self {
-        let mut b = Board {
-            n,
-            pieces: vec![vec![0; n]; n],
-        };
-        b.pieces[n / 2 - 1][n / 2] = 1;
-        b.pieces[n / 2][n / 2 - 1] = 1;
-        b.pieces[n / 2 - 1][n / 2 - 1] = -1;
-        b.pieces[n / 2][n / 2] = -1;
+    pub fn new(rows: usize, cols: usize) -> Self {
+        let mut b = Self::empty(rows, cols);
+        let (x, y) = (rows / 2, cols / 2);
+        b.set(x - 1, y, 1);
+        b.set(x, y - 1, 1);
+        b.set(x - 1, y - 1, -1);
+        b.set(x, y, -1);
         b
     }
 
-    pub fn count_diff(&self, color: i8) -> i32 {
-        let mut count: i32 = 0;
-        for row in &self.pieces {
-            for square in row {
-                if *square == color {
-                    count += 1;
-                }
-                if *square == -color {
-                    count -= 1;
-                }
-            }
+    pub fn empty(rows: usize, cols: usize) -> Self {
+        assert!(
+            rows >= 2 && cols >= 2 && rows * cols <= 64,
+            "bitboards support boards of up to 64 squares"
+        );
+        let full = if rows * cols == 64 {
+            u64::MAX
+        } else {
+            (1u64 << (rows * cols)) - 1
+        };
+        let mut first_col = 0u64;
+        for x in 0..rows {
+            first_col |= 1 << (x * cols);
+        }
+        let last_col = first_col << (cols - 1);
+
+        // a shift towards higher columns may not wrap into the first column and vice versa
+        let mut shifts = [(0, 0); 8];
+        for (i, (dx, dy)) in Self::DIRECTIONS.iter().enumerate() {
+            let amount = dx * cols as i8 + dy;
+            let mask = match dy {
+                1 => full & !first_col,
+                -1 => full & !last_col,
+                _ => full,
+            };
+            shifts[i] = (amount, mask);
+        }
+
+        Board {
+            rows,
+            cols,
+            black: 0,
+            white: 0,
+            full,
+            shifts,
         }
-        count
     }
 
-    pub fn get_legal_moves(&self, color: i8) -> Vec<(i8, i8)> {
-        let mut moves: HashSet<(i8, i8)> = HashSet::new();
-        for y in 0..self.n {
-            for x in 0..self.n {
-                if self.pieces[x][y] == color {
-                    let new_moves = self.get_moves_for_square((x as i8, y as i8));
-                    for new_move in new_moves {
-                        moves.insert(new_move);
-                    }
-                }
+    /// Returns (rows, cols).
+    pub fn size(&self) -> (usize, usize) {
+        (self.rows, self.cols)
+    }
+
+    pub fn from_pieces(pieces: &[Vec<i8>]) -> Self {
+        let mut b = Self::empty(pieces.len(), pieces[0].len());
+        for (x, row) in pieces.iter().enumerate() {
+            for (y, square) in row.iter().enumerate() {
+                b.set(x, y, *square);
             }
         }
-        Vec::from_iter(moves)
+        b
     }
 
-    pub fn has_legal_moves(&self, color: i8) -> bool {
-        for y in 0..self.n {
-            for x in 0..self.n {
-                if self.pieces[x][y] == color {
-                    let new_moves = self.get_moves_for_square((x as i8, y as i8));
-                    if new_moves.len() > 0 {
-                        return true;
-                    }
-                }
+    pub fn to_pieces(self) -> Vec<Vec<i8>> {
+        let mut pieces = vec![vec![0; self.cols]; self.rows];
+        for (x, row) in pieces.iter_mut().enumerate() {
+            for (y, square) in row.iter_mut().enumerate() {
+                *square = self.get(x, y);
             }
         }
-        false
+        pieces
     }
 
-    pub fn get_moves_for_square(&self, square: (i8, i8)) -> Vec<(i8, i8)> {
-        let color = self.pieces[square.0 as usize][square.1 as usize];
+    /// Returns the board with the colors of all discs swapped.
+    pub fn inverted(&self) -> Board {
+        Board {
+            black: self.white,
+            white: self.black,
+            ..*self
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> i8 {
+        let bit = self.bit(x, y);
+        if self.black & bit != 0 {
+            1
+        } else if self.white & bit != 0 {
+            -1
+        } else {
+            0
+        }
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, color: i8) {
+        let bit = self.bit(x, y);
+        self.black &= !bit;
+        self.white &= !bit;
+        match color {
+            1 => self.black |= bit,
+            -1 => self.white |= bit,
+            _ => {}
+        }
+    }
 
-        if color == 0 {
-            return vec![];
+    /// Returns the bitboards of (color, -color).
+    pub fn discs(&self, color: i8) -> (u64, u64) {
+        if color == 1 {
+            (self.black, self.white)
+        } else {
+            (self.white, self.black)
         }
-        let mut moves: Vec<(i8, i8)> = vec![];
-        for direction in Self::DIRECTIONS {
-            let action = self.discover_move(square, direction);
-            match action {
-                Some(x) => moves.push(x),
-                None => continue,
+    }
+
+    fn set_discs(&mut self, color: i8, own: u64, opp: u64) {
+        if color == 1 {
+            self.black = own;
+            self.white = opp;
+        } else {
+            self.white = own;
+            self.black = opp;
+        }
+    }
+
+    pub fn count_diff(&self, color: i8) -> i32 {
+        let (own, opp) = self.discs(color);
+        own.count_ones() as i32 - opp.count_ones() as i32
+    }
+
+    pub fn get_legal_moves(&self, color: i8) -> Vec<(i8, i8)> {
+        self.squares(self.legal_moves_mask(color))
+            .into_iter()
+            .map(|(x, y)| (x as i8, y as i8))
+            .collect()
+    }
+
+    pub fn has_legal_moves(&self, color: i8) -> bool {
+        self.legal_moves_mask(color) != 0
+    }
+
+    /// Returns a bitboard with a bit set for every legal move of color.
+    pub fn legal_moves_mask(&self, color: i8) -> u64 {
+        let (own, opp) = self.discs(color);
+        let empty = self.full & !(own | opp);
+        let mut moves = 0;
+        for d in 0..Self::DIRECTIONS.len() {
+            // a line can hold at most max(rows, cols) - 2 discs of the opponent
+            let mut line = self.shift(own, d) & opp;
+            for _ in 3..self.rows.max(self.cols) {
+                line |= self.shift(line, d) & opp;
             }
+            moves |= self.shift(line, d) & empty;
         }
         moves
     }
 
-    pub fn execute_move(&mut self, action: (i8, i8), color: i8) {
-        for direction in Self::DIRECTIONS {
-            for flip in self.get_flips(action, direction, color) {
-                self.pieces[flip.0 as usize][flip.1 as usize] = color;
-            }
+    pub fn execute_move(&mut self, action: (i8, i8), color: i8) -> MoveUndo {
+        let bit = self.bit(action.0 as usize, action.1 as usize);
+        let mut undo = MoveUndo::pass(color);
+        if let Some(flips) = self.get_flips(bit, color) {
+            let (own, opp) = self.discs(color);
+            let changed = flips | bit;
+            undo.placed = bit & !(own | opp);
+            undo.flipped = changed & opp;
+            self.set_discs(color, own | changed, opp & !changed);
         }
+        undo
     }
 
-    pub fn discover_move(&self, origin: (i8, i8), direction: (i8, i8)) -> Option<(i8, i8)> {
-        let color = self.pieces[origin.0 as usize][origin.1 as usize];
-        let mut flips: Vec<(i8, i8)> = vec![];
+    pub fn undo_move(&mut self, undo: &MoveUndo) {
+        let (own, opp) = self.discs(undo.color);
+        self.set_discs(
+            undo.color,
+            own & !(undo.placed | undo.flipped),
+            opp | undo.flipped,
+        );
+    }
 
-        for (x, y) in self.increment_move(origin, direction, self.n) {
-            if self.pieces[x as usize][y as usize] == 0 {
-                if flips.len() > 0 {
-                    return Some((x, y));
-                }
-                return None;
-            }
-            if self.pieces[x as usize][y as usize] == color {
-                return None;
-            }
-            if self.pieces[x as usize][y as usize] == -color {
-                flips.push((x, y));
-            }
+    /// Returns the (x, y) coordinates of every bit set in squares.
+    pub fn squares(&self, mut squares: u64) -> Vec<(usize, usize)> {
+        let mut output = Vec::with_capacity(squares.count_ones() as usize);
+        while squares != 0 {
+            let i = squares.trailing_zeros() as usize;
+            output.push((i / self.cols, i % self.cols));
+            squares &= squares - 1;
         }
-        None
+        output
     }
 
-    pub fn get_flips(&self, origin: (i8, i8), direction: (i8, i8), color: i8) -> Vec<(i8, i8)> {
-        let mut flips: Vec<(i8, i8)> = vec![origin];
-        for dir in self.increment_move(origin, direction, self.n) {
-            if self.pieces[dir.0 as usize][dir.1 as usize] == 0 {
-                return vec![];
+    /// Returns the discs flipped when color plays on square, or None if no
+    /// line starting at square ends in a disc of color.
+    pub fn get_flips(&self, square: u64, color: i8) -> Option<u64> {
+        let (own, opp) = self.discs(color);
+        let mut flips = 0;
+        let mut bracketed = false;
+        for d in 0..Self::DIRECTIONS.len() {
+            let mut line = 0;
+            let mut next = self.shift(square, d);
+            while next & opp != 0 {
+                line |= next;
+                next = self.shift(next, d);
             }
-            if self.pieces[dir.0 as usize][dir.1 as usize] == -color {
-                flips.push(dir);
-            } else {
-                return flips;
+            if next & own != 0 {
+                flips |= line;
+                bracketed = true;
             }
         }
-        vec![]
+        if bracketed {
+            Some(flips)
+        } else {
+            None
+        }
     }
 
-    pub fn increment_move(&self, action: (i8, i8), direction: (i8, i8), n: usize) -> Vec<(i8, i8)> {
-        let mut output: Vec<(i8, i8)> = Vec::new();
-        let mut x: i8 = action.0 + direction.0;
-        let mut y: i8 = action.1 + direction.1;
-        while x >= 0 && x < n as i8 && y >= 0 && y < n as i8 {
-            output.push((x.clone(), y.clone()));
-            x += direction.0;
-            y += direction.1;
+    fn shift(&self, b: u64, direction: usize) -> u64 {
+        let (amount, mask) = self.shifts[direction];
+        if amount > 0 {
+            (b << amount) & mask
+        } else {
+            (b >> -amount) & mask
         }
-        output
     }
 
-    // pub fn get_item(&self, i: usize) -> &Vec<i8> {
-    //     &self.pieces[i]
-    // }
+    fn bit(&self, x: usize, y: usize) -> u64 {
+        1 << (x * self.cols + y)
+    }
+}
+
+impl From<Board> for Vec<Vec<i8>> {
+    fn from(board: Board) -> Self {
+        board.to_pieces()
+    }
+}
 
-    // pub fn print_board(&self) {
-    //     println!("board:");
-    //     for row in &self.pieces {
-    //         println!("{:?}", row);
-    //     }
-    // }
+impl TryFrom<Vec<Vec<i8>>> for Board {
+    type Error = PiecesError;
 
-    // pub fn print_vec_board(board: &Vec<Vec<i8>>) {
-    //     println!("board:");
-    //     for row in board {
-    //         println!("{:?}", row);
-    //     }
-    // }
+    fn try_from(pieces: Vec<Vec<i8>>) -> Result<Self, Self::Error> {
+        let Some(first) = pieces.first() else {
+            return Err(PiecesError::Empty);
+        };
+        let (rows, cols) = (pieces.len(), first.len());
+        if pieces.iter().any(|row| row.len() != cols) {
+            return Err(PiecesError::RaggedRows);
+        }
+        if rows < 2 || cols < 2 || rows * cols > 64 {
+            return Err(PiecesError::InvalidSize { rows, cols });
+        }
+        if let Some(square) = pieces.iter().flatten().find(|s| !(-1..=1).contains(*s)) {
+            return Err(PiecesError::InvalidSquare(*square));
+        }
+        Ok(Board::from_pieces(&pieces))
+    }
 }
+
+#[cfg(test)]
+mod tests;
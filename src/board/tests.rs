@@ -0,0 +1,270 @@
+use rand::{seq::SliceRandom, Rng};
+
+use super::{Board, PiecesError};
+
+/// The square-by-square board that `Board` replaced, kept as a reference.
+struct ReferenceBoard {
+    rows: usize,
+    cols: usize,
+    pieces: Vec<Vec<i8>>,
+}
+
+impl ReferenceBoard {
+    fn count_diff(&self, color: i8) -> i32 {
+        let mut count = 0;
+        for row in &self.pieces {
+            for square in row {
+                if *square == color {
+                    count += 1;
+                }
+                if *square == -color {
+                    count -= 1;
+                }
+            }
+        }
+        count
+    }
+
+    fn get_legal_moves(&self, color: i8) -> Vec<(i8, i8)> {
+        let mut moves = Vec::new();
+        for x in 0..self.rows {
+            for y in 0..self.cols {
+                if self.pieces[x][y] != color {
+                    continue;
+                }
+                for direction in Board::DIRECTIONS {
+                    let mut seen_opponent = false;
+                    for (i, j) in self.increment_move((x as i8, y as i8), direction) {
+                        let square = self.pieces[i as usize][j as usize];
+                        if square == 0 {
+                            if seen_opponent && !moves.contains(&(i, j)) {
+                                moves.push((i, j));
+                            }
+                            break;
+                        }
+                        if square == color {
+                            break;
+                        }
+                        seen_opponent = true;
+                    }
+                }
+            }
+        }
+        moves.sort();
+        moves
+    }
+
+    fn execute_move(&mut self, action: (i8, i8), color: i8) {
+        for direction in Board::DIRECTIONS {
+            let mut flips = vec![action];
+            let mut closed = false;
+            for (i, j) in self.increment_move(action, direction) {
+                let square = self.pieces[i as usize][j as usize];
+                if square == -color {
+                    flips.push((i, j));
+                } else {
+                    closed = square == color;
+                    break;
+                }
+            }
+            if closed {
+                for (i, j) in flips {
+                    self.pieces[i as usize][j as usize] = color;
+                }
+            }
+        }
+    }
+
+    fn increment_move(&self, action: (i8, i8), direction: (i8, i8)) -> Vec<(i8, i8)> {
+        let mut output = Vec::new();
+        let mut x = action.0 + direction.0;
+        let mut y = action.1 + direction.1;
+        while x >= 0 && x < self.rows as i8 && y >= 0 && y < self.cols as i8 {
+            output.push((x, y));
+            x += direction.0;
+            y += direction.1;
+        }
+        output
+    }
+}
+
+fn assert_matches_reference(rows: usize, cols: usize, games: usize) {
+    let mut rng = rand::thread_rng();
+    for _ in 0..games {
+        let mut board = Board::new(rows, cols);
+        let mut reference = ReferenceBoard {
+            rows,
+            cols,
+            pieces: board.to_pieces(),
+        };
+        let mut color = 1;
+        loop {
+            assert_eq!(board.to_pieces(), reference.pieces);
+            assert_eq!(board.count_diff(color), reference.count_diff(color));
+
+            let mut moves = board.get_legal_moves(color);
+            moves.sort();
+            assert_eq!(moves, reference.get_legal_moves(color));
+            assert_eq!(board.has_legal_moves(color), !moves.is_empty());
+
+            // an arbitrary square, legal or not, must change both boards alike
+            let square = (rng.gen_range(0..rows) as i8, rng.gen_range(0..cols) as i8);
+            let mut probe = board;
+            let mut probe_reference = ReferenceBoard {
+                rows,
+                cols,
+                pieces: reference.pieces.clone(),
+            };
+            let undo = probe.execute_move(square, color);
+            probe_reference.execute_move(square, color);
+            assert_eq!(probe.to_pieces(), probe_reference.pieces);
+            probe.undo_move(&undo);
+            assert_eq!(probe, board);
+
+            if moves.is_empty() {
+                if !board.has_legal_moves(-color) {
+                    break;
+                }
+            } else {
+                let m = *moves.choose(&mut rng).unwrap();
+                let before = board;
+                let undo = board.execute_move(m, color);
+                reference.execute_move(m, color);
+                assert_eq!(undo.placed.count_ones(), 1);
+                assert!(undo.flipped != 0);
+                let mut undone = board;
+                undone.undo_move(&undo);
+                assert_eq!(undone, before);
+            }
+            color = -color;
+        }
+    }
+}
+
+#[test]
+fn new_4() {
+    let board = Board::new(4, 4);
+    let expected_board = "[[0, 0, 0, 0], [0, -1, 1, 0], [0, 1, -1, 0], [0, 0, 0, 0]]";
+    assert_eq!(format!("{:?}", board.to_pieces()), expected_board);
+}
+
+#[test]
+fn new_4x6() {
+    let board = Board::new(4, 6);
+    let expected_board =
+        "[[0, 0, 0, 0, 0, 0], [0, 0, -1, 1, 0, 0], [0, 0, 1, -1, 0, 0], [0, 0, 0, 0, 0, 0]]";
+    assert_eq!(format!("{:?}", board.to_pieces()), expected_board);
+}
+
+#[test]
+fn from_pieces_round_trip() {
+    let pieces = vec![
+        vec![0, -1, -1, -1],
+        vec![-1, -1, 1, -1],
+        vec![0, -1, -1, -1],
+        vec![-1, 0, -1, -1],
+    ];
+    let board = Board::from_pieces(&pieces);
+    assert_eq!(board.to_pieces(), pieces);
+    assert_eq!(board.count_diff(1), -11);
+}
+
+#[test]
+fn serialized_as_pieces() {
+    let mut board = Board::new(4, 6);
+    board.execute_move((0, 3), 1);
+    let serialized = serde_pickle::to_vec(&board, Default::default()).unwrap();
+    assert_eq!(
+        serialized,
+        serde_pickle::to_vec(&board.to_pieces(), Default::default()).unwrap()
+    );
+    let deserialized: Board = serde_pickle::from_slice(&serialized, Default::default()).unwrap();
+    assert_eq!(deserialized, board);
+}
+
+#[test]
+fn legal_moves_8() {
+    let board = Board::new(8, 8);
+    let mut moves = board.get_legal_moves(1);
+    moves.sort();
+    assert_eq!(moves, vec![(2, 3), (3, 2), (4, 5), (5, 4)]);
+}
+
+#[test]
+fn execute_move_undo_record() {
+    let mut board = Board::new(4, 4);
+    let before = board;
+    let undo = board.execute_move((0, 1), 1);
+    assert_eq!(board.squares(undo.placed), vec![(0, 1)]);
+    assert_eq!(board.squares(undo.flipped), vec![(1, 1)]);
+    board.undo_move(&undo);
+    assert_eq!(board, before);
+}
+
+#[test]
+fn matches_reference_4() {
+    assert_matches_reference(4, 4, 200);
+}
+
+#[test]
+fn matches_reference_6() {
+    assert_matches_reference(6, 6, 100);
+}
+
+#[test]
+fn matches_reference_8() {
+    assert_matches_reference(8, 8, 50);
+}
+
+#[test]
+fn matches_reference_4x6() {
+    assert_matches_reference(4, 6, 100);
+}
+
+#[test]
+fn matches_reference_8x6() {
+    assert_matches_reference(8, 6, 50);
+}
+
+#[test]
+fn matches_reference_2x8() {
+    assert_matches_reference(2, 8, 50);
+}
+
+#[test]
+fn pieces_round_trip() {
+    let board = Board::new(4, 6);
+    assert_eq!(Board::try_from(board.to_pieces()), Ok(board));
+    let json = serde_json::to_string(&board).unwrap();
+    assert_eq!(serde_json::from_str::<Board>(&json).unwrap(), board);
+}
+
+#[test]
+fn invalid_pieces_are_rejected() {
+    assert_eq!(Board::try_from(vec![]), Err(PiecesError::Empty));
+    assert_eq!(
+        Board::try_from(vec![vec![0; 4], vec![0; 3], vec![0; 4], vec![0; 4]]),
+        Err(PiecesError::RaggedRows)
+    );
+    assert_eq!(
+        Board::try_from(vec![vec![0; 10]; 8]),
+        Err(PiecesError::InvalidSize { rows: 8, cols: 10 })
+    );
+    assert_eq!(
+        Board::try_from(vec![vec![0; 1]; 4]),
+        Err(PiecesError::InvalidSize { rows: 4, cols: 1 })
+    );
+    let mut pieces = vec![vec![0; 4]; 4];
+    pieces[2][3] = 2;
+    assert_eq!(Board::try_from(pieces), Err(PiecesError::InvalidSquare(2)));
+
+    // deserializing fails instead of panicking
+    for json in [
+        "[]",
+        "[[0,0],[0]]",
+        "[[0,0,0,0,0,0,0,0,0]]",
+        "[[0,0],[0,-2]]",
+    ] {
+        assert!(serde_json::from_str::<Board>(json).is_err());
+    }
+}
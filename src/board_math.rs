@@ -1,5 +1,12 @@
+/// The 2D and 1D index tables of a symmetry of a board.
+pub type Symmetry<'a> = (&'a Vec<Vec<(usize, usize)>>, &'a Vec<usize>);
+
+/// Index tables for the symmetries of a board. The rotations by 90 degrees
+/// and their mirrors only exist for square boards and are empty otherwise.
 #[derive(Clone)]
 pub struct BoardMath {
+    pub rows: usize,
+    pub cols: usize,
     pub mirror_2d: Vec<Vec<(usize, usize)>>,
     pub l90_2d: Vec<Vec<(usize, usize)>>,
     pub l90_2d_mirror: Vec<Vec<(usize, usize)>>,
@@ -17,8 +24,8 @@ pub struct BoardMath {
 }
 
 impl BoardMath {
-    pub fn new(n: usize) -> Self {
-        let coords: Vec<Vec<(usize, usize)>> = Self::coordinates(n);
+    pub fn new(rows: usize, cols: usize) -> Self {
+        let coords: Vec<Vec<(usize, usize)>> = Self::coordinates(rows, cols);
 
         // mirror (reverse rows)
         let mut mirror_2d = coords.clone();
@@ -26,15 +33,26 @@ impl BoardMath {
             row.reverse();
         }
 
+        // a rotation by 90 degrees turns a rows x cols board into a cols x rows board
+        let square = rows == cols;
+
         // left rotation 90 degrees 2D array (transpose -> reverse rows)
-        let mut l90_2d = Self::transpose2(coords.clone());
+        let mut l90_2d = if square {
+            Self::transpose2(coords.clone())
+        } else {
+            Vec::new()
+        };
         let l90_2d_mirror = l90_2d.clone();
         for row in &mut l90_2d {
             row.reverse();
         }
 
         // right rotation 90 degrees 2D array (transpose -> reverse columns)
-        let mut r90_2d = Self::transpose2(coords.clone());
+        let mut r90_2d = if square {
+            Self::transpose2(coords.clone())
+        } else {
+            Vec::new()
+        };
         r90_2d.reverse();
         let mut r90_2d_mirror = r90_2d.clone();
         for row in &mut r90_2d_mirror {
@@ -49,16 +67,18 @@ impl BoardMath {
             row.reverse();
         }
 
-        let mirror_1d = Self::flatten(&mirror_2d);
-        let l90_1d = Self::flatten(&l90_2d);
-        let l90_1d_mirror = Self::flatten(&l90_2d_mirror);
-        let r90_1d = Self::flatten(&r90_2d);
-        let r90_1d_mirror = Self::flatten(&r90_2d_mirror);
-        let l180_1d = Self::flatten(&l180_2d);
-        let l180_1d_mirror = Self::flatten(&l180_2d_mirror);
+        let mirror_1d = Self::flatten(&mirror_2d, cols);
+        let l90_1d = Self::flatten(&l90_2d, cols);
+        let l90_1d_mirror = Self::flatten(&l90_2d_mirror, cols);
+        let r90_1d = Self::flatten(&r90_2d, cols);
+        let r90_1d_mirror = Self::flatten(&r90_2d_mirror, cols);
+        let l180_1d = Self::flatten(&l180_2d, cols);
+        let l180_1d_mirror = Self::flatten(&l180_2d_mirror, cols);
 
         // create class
         let b = BoardMath {
+            rows,
+            cols,
             mirror_2d,
             l90_2d,
             l90_2d_mirror,
@@ -77,6 +97,29 @@ impl BoardMath {
         b
     }
 
+    pub fn is_square(&self) -> bool {
+        self.rows == self.cols
+    }
+
+    /// Returns the (2D, 1D) tables of every symmetry except the identity: seven
+    /// for a square board, three for a rectangular one.
+    pub fn symmetries(&self) -> Vec<Symmetry<'_>> {
+        let mut symmetries = vec![(&self.mirror_2d, &self.mirror_1d)];
+        if self.is_square() {
+            symmetries.extend([
+                (&self.l90_2d, &self.l90_1d),
+                (&self.l90_2d_mirror, &self.l90_1d_mirror),
+                (&self.r90_2d, &self.r90_1d),
+                (&self.r90_2d_mirror, &self.r90_1d_mirror),
+            ]);
+        }
+        symmetries.extend([
+            (&self.l180_2d, &self.l180_1d),
+            (&self.l180_2d_mirror, &self.l180_1d_mirror),
+        ]);
+        symmetries
+    }
+
     pub fn apply_2d(b: &Vec<Vec<i8>>, t: &Vec<Vec<(usize, usize)>>) -> Vec<Vec<i8>> {
         let mut r = b.clone();
         for x in 0..b.len() {
@@ -87,31 +130,23 @@ impl BoardMath {
         r
     }
 
-    pub fn apply_1d(b: &Vec<f32>, t: &Vec<usize>) -> Vec<f32> {
-        let mut r = b.clone();
+    pub fn apply_1d(b: &[f32], t: &Vec<usize>) -> Vec<f32> {
+        let mut r = b.to_vec();
         for x in 0..t.len() {
             r[x] = b[t[x]];
         }
         r
     }
 
-    fn flatten(b_2d: &Vec<Vec<(usize, usize)>>) -> Vec<usize> {
-        let dim = b_2d.len();
-        let mut b_1d = Vec::with_capacity(b_2d.len() * b_2d.len());
-        unsafe { b_1d.set_len(b_2d.len() * b_2d.len()) }
-        for x in 0..b_2d.len() {
-            for y in 0..b_2d.len() {
-                b_1d[x * dim + y] = b_2d[x][y].0 * dim + b_2d[x][y].1;
-            }
-        }
-        b_1d
+    fn flatten(b_2d: &[Vec<(usize, usize)>], cols: usize) -> Vec<usize> {
+        b_2d.iter().flatten().map(|(x, y)| x * cols + y).collect()
     }
 
-    fn coordinates(n: usize) -> Vec<Vec<(usize, usize)>> {
-        let mut coords: Vec<Vec<(usize, usize)>> = Vec::with_capacity(n);
-        for x in 0..n {
-            coords.push(Vec::with_capacity(n));
-            for y in 0..n {
+    fn coordinates(rows: usize, cols: usize) -> Vec<Vec<(usize, usize)>> {
+        let mut coords: Vec<Vec<(usize, usize)>> = Vec::with_capacity(rows);
+        for x in 0..rows {
+            coords.push(Vec::with_capacity(cols));
+            for y in 0..cols {
                 coords[x].push((x, y));
             }
         }
@@ -2,14 +2,21 @@ use burn::tensor::backend::AutodiffBackend;
 use rand::distributions::Distribution;
 use rand::seq::SliceRandom;
 use rand::{distributions::WeightedIndex, thread_rng};
+use std::cell::Cell;
 use std::collections::{HashMap, VecDeque};
 use std::fs::{self, File};
 use std::io::Write;
-use std::time::SystemTime;
+use std::path::Path;
+use std::time::{Instant, SystemTime};
 
 use crate::arena::Arena;
-use crate::othello::Othello;
-use crate::{game::Game, mcts::MCTS, n_net::NNetWrapper, neural_net::NeuralNet};
+use crate::game_record::{self, GameRecord, MoveRecord};
+use crate::{
+    game::{self, Example, Game},
+    mcts::{SearchBudget, SearchReport, StopReason, TreeMemory, MCTS},
+    n_net::NNetWrapper,
+    neural_net::NeuralNet,
+};
 
 pub struct Coach<G, B>
 where
@@ -21,8 +28,10 @@ where
     pnet: NNetWrapper<B, G>,
     args: HashMap<String, String>,
     mcts: MCTS<G, B>,
-    training_examples_history: VecDeque<Vec<(Vec<Vec<i8>>, Vec<f32>, i8)>>,
+    training_examples_history: VecDeque<Vec<Example<G>>>,
     skip_first_self_play: bool,
+    // largest search tree of the self-play of the iteration
+    largest_tree: TreeMemory,
 }
 
 impl<G: Clone, B> Coach<G, B>
@@ -30,27 +39,66 @@ where
     G: Game,
     B: AutodiffBackend,
 {
-    pub fn new(
-        game: G,
-        nnet: NNetWrapper<B, G>,
-        device: B::Device,
-        args: HashMap<String, String>,
-    ) -> Self {
+    /// The previous network of the arena starts as a copy of nnet, so that it
+    /// has the same model. Panics if args give the searches no limit, rather
+    /// than in the middle of self-play.
+    pub fn new(game: G, nnet: NNetWrapper<B, G>, args: HashMap<String, String>) -> Self {
+        assert!(
+            SearchBudget::from_args(&args).is_limited(),
+            "numMCTSSims, searchTimeMs, searchNodes and searchMemoryMb are all 0: the searches would never stop"
+        );
         Coach {
             game: game.clone(),
             nnet: nnet.clone(),
-            pnet: NNetWrapper::new(game.clone(), device),
+            pnet: nnet.clone(),
             args: args.clone(),
             mcts: MCTS::new(game.clone(), nnet.clone(), args.clone()),
             training_examples_history: VecDeque::new(),
             skip_first_self_play: false,
+            largest_tree: TreeMemory::default(),
         }
     }
 
-    fn execute_episode(&mut self) -> Vec<(Vec<Vec<i8>>, Vec<f32>, i8)> {
-        let mut train_examples = Vec::<(Vec<Vec<i8>>, Vec<f32>, i8)>::new();
-        let mut board = self.game.get_init_board().clone();
+    /// Returns a search for self-play, which reuses its tree from move to move
+    /// when selfPlayReuseTree is set and mixes Dirichlet noise of
+    /// dirichletAlpha into the priors of its roots with weight
+    /// dirichletEpsilon. Its searches never stop early, since their visits are
+    /// the training targets.
+    fn self_play_mcts(&self) -> MCTS<G, B> {
+        let reuse_tree = self
+            .args
+            .get("selfPlayReuseTree")
+            .is_some_and(|v| v.parse::<bool>().unwrap());
+        let budget = SearchBudget {
+            early_stop: false,
+            ..SearchBudget::from_args(&self.args)
+        };
+        let mcts = MCTS::new(self.game.clone(), self.nnet.clone(), self.args.clone())
+            .with_tree_reuse(reuse_tree)
+            .with_budget(budget);
+        let epsilon = self
+            .args
+            .get("dirichletEpsilon")
+            .map_or(0., |v| v.parse::<f32>().unwrap());
+        if epsilon == 0. {
+            return mcts;
+        }
+        let alpha = self
+            .args
+            .get("dirichletAlpha")
+            .unwrap()
+            .parse::<f32>()
+            .unwrap();
+        mcts.with_root_noise(alpha, epsilon)
+    }
+
+    /// Plays one game of self-play and adds its record to records.
+    fn execute_episode(&mut self, records: &mut Vec<GameRecord<G::State>>) -> Vec<Example<G>> {
+        let mut train_examples = Vec::<(G::State, Vec<f32>, i8)>::new();
+        let mut board = self.game.get_start_board();
         let mut cur_player = 1;
+        let mut record =
+            GameRecord::new(board.clone(), cur_player).with_players("self-play", "self-play");
         let mut episode_step = 0;
 
         loop {
@@ -64,7 +112,13 @@ where
                 .unwrap();
             let temp = if episode_step < temp_threshold { 1 } else { 0 };
 
+            let now = Instant::now();
             let pi = self.mcts.get_action_prob(&canonical_board, temp);
+            let time_ms = now.elapsed().as_secs_f32() * 1000.;
+            let memory = self.mcts.memory();
+            if memory.bytes > self.largest_tree.bytes {
+                self.largest_tree = memory;
+            }
             let sym = self.game.get_symmetries(&canonical_board, &pi);
             for s in sym {
                 let b = s.0;
@@ -75,18 +129,33 @@ where
 
             let weighted_index = WeightedIndex::new(&pi).unwrap();
             let mut rng = thread_rng();
-            let action = weighted_index.sample(&mut rng);
-            let next_state = self.game.get_next_state(&board, cur_player, action as u8);
+            let action = self.game.index_to_action(weighted_index.sample(&mut rng));
+            let (counts, value) = self.mcts.root_stats(&canonical_board);
+            record.moves.push(
+                MoveRecord::new(self.game.action_to_string(action))
+                    .with_visits(&counts)
+                    .with_value(value)
+                    .with_time_ms(time_ms),
+            );
+            let next_state = self.game.get_next_state(&board, cur_player, action);
             board = next_state.0;
             cur_player = next_state.1;
 
-            let r = self.game.get_game_ended(&board, cur_player);
-
-            if r != 0 {
-                for tup in train_examples.iter_mut() {
-                    tup.2 = r * ((-1 as i8).pow(if tup.2 != cur_player { 1 } else { 0 }));
-                }
-                return train_examples;
+            if let Some(r) = self.game.get_game_ended(&board, cur_player) {
+                record.result = Some(if cur_player == 1 { r } else { r.for_opponent() });
+                let draw_value = self.args.get("drawValue").unwrap().parse::<f32>().unwrap();
+                records.push(record);
+                return train_examples
+                    .into_iter()
+                    .map(|(b, p, player)| {
+                        let result = if player == cur_player {
+                            r
+                        } else {
+                            r.for_opponent()
+                        };
+                        (b, p, result.value(draw_value))
+                    })
+                    .collect();
             }
         }
     }
@@ -115,13 +184,17 @@ where
             // examples of the iteration
             if !&self.skip_first_self_play || i > 1 {
                 println!("Not skipping first self play");
-                let mut iteration_train_examples: VecDeque<Vec<(Vec<Vec<i8>>, Vec<f32>, i8)>> =
+                let mut iteration_train_examples: VecDeque<Vec<Example<G>>> =
                     VecDeque::with_capacity(maxlen_of_queue);
+                let mut records = Vec::with_capacity(num_eps as usize);
 
+                self.largest_tree = TreeMemory::default();
                 for _j in 0..num_eps {
-                    self.mcts = MCTS::new(self.game.clone(), self.nnet.clone(), self.args.clone());
-                    iteration_train_examples.push_back(self.execute_episode());
+                    self.mcts = self.self_play_mcts();
+                    iteration_train_examples.push_back(self.execute_episode(&mut records));
                 }
+                println!("Largest search tree: {}", self.largest_tree);
+                self.save_games(&format!("games_{i}.bin"), &records);
 
                 // save the iteration examples to the history
                 self.training_examples_history
@@ -142,6 +215,15 @@ where
             for e in self.training_examples_history.clone() {
                 train_examples.extend(e);
             }
+            if self
+                .args
+                .get("deduplicateExamples")
+                .is_some_and(|v| v.parse::<bool>().unwrap())
+            {
+                let count = train_examples.len();
+                train_examples = game::deduplicate_examples(&self.game, &train_examples);
+                println!("Merged {count} examples into {}", train_examples.len());
+            }
             train_examples.shuffle(&mut thread_rng());
 
             // train new network, keeping a copy of the old one
@@ -149,14 +231,29 @@ where
                 .save_checkpoint(self.args.get("checkpoint").unwrap(), "temp.pth.tar");
             self.pnet
                 .load_checkpoint(self.args.get("checkpoint").unwrap(), "temp.pth.tar");
-            let mut pmcts = MCTS::new(self.game.clone(), self.pnet.clone(), self.args.clone());
+            let arena_reuse_tree = self
+                .args
+                .get("arenaReuseTree")
+                .is_some_and(|v| v.parse::<bool>().unwrap());
+            let mut pmcts = MCTS::new(self.game.clone(), self.pnet.clone(), self.args.clone())
+                .with_tree_reuse(arena_reuse_tree);
 
             self.nnet.train(&train_examples);
-            let mut nmcts = MCTS::new(self.game.clone(), self.nnet.clone(), self.args.clone());
+            let mut nmcts = MCTS::new(self.game.clone(), self.nnet.clone(), self.args.clone())
+                .with_tree_reuse(arena_reuse_tree);
 
             println!("PITTING AGAINST PREVIOUS VERSION");
-            let lambda1 = |x: &Vec<Vec<i8>>| {
+            let game = &self.game;
+            // moves of the arena, their simulations and the searches that stopped once decided
+            let searches = Cell::new((0, 0, 0));
+            let count_search = |report: SearchReport| {
+                let (moves, sims, decided) = searches.get();
+                let early = (report.reason == StopReason::Decided) as usize;
+                searches.set((moves + 1, sims + report.simulations, decided + early));
+            };
+            let lambda1 = |x: &G::State| {
                 let pi = pmcts.get_action_prob(x, 0);
+                count_search(pmcts.last_search().unwrap());
                 let max = pi.iter().cloned().fold(0. / 0., f32::max);
                 let mut best_as = Vec::new();
                 for i in 0..pi.len() {
@@ -164,10 +261,11 @@ where
                         best_as.push(i);
                     }
                 }
-                return best_as.first().unwrap().to_owned();
+                game.index_to_action(*best_as.first().unwrap())
             };
-            let lambda2 = |x: &Vec<Vec<i8>>| {
+            let lambda2 = |x: &G::State| {
                 let pi = nmcts.get_action_prob(x, 0);
+                count_search(nmcts.last_search().unwrap());
                 let max = pi.iter().cloned().fold(0. / 0., f32::max);
                 let mut best_as = Vec::new();
                 for i in 0..pi.len() {
@@ -175,9 +273,10 @@ where
                         best_as.push(i);
                     }
                 }
-                return best_as.first().unwrap().to_owned();
+                game.index_to_action(*best_as.first().unwrap())
             };
-            let mut arena = Arena::new(lambda1, lambda2, &self.game, Othello::display);
+            let mut arena = Arena::new(lambda1, lambda2, game, |b: &G::State| game.display(b))
+                .with_names("previous", "new");
             let arena_compare = self
                 .args
                 .get("arenaCompare")
@@ -186,6 +285,17 @@ where
                 .unwrap();
             let verbose = self.args.get("verbose").unwrap().parse::<bool>().unwrap();
             let results = arena.play_games(arena_compare, verbose);
+            let (moves, sims, decided) = searches.get();
+            if moves > 0 {
+                println!(
+                    "Arena searches: {:.0} simulations per move, {} of {} stopped once decided",
+                    sims as f32 / moves as f32,
+                    decided,
+                    moves
+                );
+            }
+            let records = arena.take_records();
+            self.save_games(&format!("arena_{i}.bin"), &records);
             let pwins = results.0;
             let nwins = results.1;
             let draws = results.2;
@@ -227,6 +337,23 @@ where
         return format!("checkpoint_{iteration}.pth.tar");
     }
 
+    /// Saves the records of games to the checkpoint folder when saveGames is
+    /// set.
+    fn save_games(&self, filename: &str, records: &[GameRecord<G::State>]) {
+        if !self
+            .args
+            .get("saveGames")
+            .is_some_and(|v| v.parse::<bool>().unwrap())
+        {
+            return;
+        }
+        let folder = self.args.get("checkpoint").unwrap();
+        fs::create_dir_all(folder).expect("Should be able to create path");
+        if let Err(e) = game_record::save_records(&Path::new(folder).join(filename), records) {
+            println!("Could not save the games to {filename}: {e}");
+        }
+    }
+
     fn save_train_examples(&self, iteration: i32) {
         let folder = self.args.get("checkpoint").unwrap();
         let filename = self.get_checkpoint_file(iteration.to_string());
@@ -239,12 +366,38 @@ where
         let _ = buffer.write_all(&serialized);
     }
 
+    /// Adds the training examples of the games recorded in trainGames to the
+    /// history, as one entry.
+    pub fn load_train_games(&mut self) {
+        let path = self.args.get("trainGames").unwrap();
+        let draw_value = self.args.get("drawValue").unwrap().parse::<f32>().unwrap();
+        let records: Vec<GameRecord<G::State>> = match game_record::load_records(Path::new(path)) {
+            Ok(records) => records,
+            Err(e) => return println!("Could not load the games in {path}: {e}"),
+        };
+        let mut examples = Vec::new();
+        for (i, record) in records.iter().enumerate() {
+            match record.training_examples(&self.game, draw_value) {
+                Ok(e) => examples.extend(e),
+                Err(e) => println!("Skipping game {i} of {path}: {e}"),
+            }
+        }
+        println!(
+            "Loaded {} examples from {} games",
+            examples.len(),
+            records.len()
+        );
+        self.training_examples_history.push_back(examples);
+    }
+
     pub fn load_train_examples(&mut self) {
         let folder = self.args.get("checkpoint").unwrap();
         let filename = self.args.get("load_examples_file").unwrap();
         let file_path = format!("{folder}/{filename}");
         let serialized = fs::read(file_path).unwrap();
 
-        self.training_examples_history = serde_pickle::from_slice::<VecDeque<Vec<(Vec<i8>, Vec<f32>, i8)>>>(&serialized, Default::default()).unwrap();
+        self.training_examples_history =
+            serde_pickle::from_slice::<VecDeque<Vec<Example<G>>>>(&serialized, Default::default())
+                .unwrap();
     }
 }
@@ -0,0 +1,243 @@
+use crate::{
+    game::{Game, GameResult},
+    mnk_game::has_line,
+    zobrist::Zobrist,
+};
+
+/// Connect Four: players take turns dropping a disc into a column, where it
+/// falls onto the lowest empty square. The first player with four discs in a
+/// row, column or diagonal wins, and a full board is a draw.
+///
+/// Row 0 is the top row of the board. Actions are column numbers and there
+/// is no pass.
+#[derive(Clone)]
+pub struct ConnectFour {
+    rows: usize,
+    cols: usize,
+    init_board: Vec<Vec<i8>>,
+    zobrist: Zobrist,
+}
+
+impl ConnectFour {
+    /// Number of discs in a row needed to win.
+    const WIN_LENGTH: usize = 4;
+
+    const SQUARE_CONTENT: [(i8, &'static str); 3] = [(-1, "O"), (0, "-"), (1, "X")];
+
+    /// Connect Four on a board of rows x cols squares, with no more than 64
+    /// squares in total.
+    pub fn new_rect(rows: usize, cols: usize) -> Self {
+        assert!(
+            rows * cols <= 64,
+            "Zobrist hashing supports boards of up to 64 squares"
+        );
+        ConnectFour {
+            rows,
+            cols,
+            init_board: vec![vec![0; cols]; rows],
+            zobrist: Zobrist::new(rows * cols),
+        }
+    }
+
+    /// Returns the row a disc dropped into col lands on, or None when the
+    /// column is full.
+    fn landing_row(&self, board: &[Vec<i8>], col: usize) -> Option<usize> {
+        (0..self.rows).rev().find(|x| board[*x][col] == 0)
+    }
+
+    fn square_content(color: i8) -> &'static str {
+        let mut content = "";
+        for tup in Self::SQUARE_CONTENT {
+            if tup.0 == color {
+                content = tup.1;
+            }
+        }
+        content
+    }
+}
+
+impl Game for ConnectFour {
+    type State = Vec<Vec<i8>>;
+    /// The column to drop a disc into.
+    type Action = usize;
+    /// The (row, column) of the dropped disc.
+    type Undo = (usize, usize);
+
+    /// The board has n rows and n + 1 columns, so new(6) is the standard
+    /// 6 x 7 board.
+    fn new(n: usize) -> Self {
+        Self::new_rect(n, n + 1)
+    }
+
+    fn get_init_board(&self) -> &Vec<Vec<i8>> {
+        &self.init_board
+    }
+
+    fn get_board_size(&self) -> (usize, usize) {
+        (self.rows, self.cols)
+    }
+
+    fn get_state_size(&self, board: &Self::State) -> (usize, usize) {
+        (board.len(), board.first().map_or(0, |row| row.len()))
+    }
+
+    fn get_action_size(&self) -> usize {
+        self.cols
+    }
+
+    fn action_to_index(&self, action: usize) -> usize {
+        action
+    }
+
+    fn index_to_action(&self, index: usize) -> usize {
+        index
+    }
+
+    fn get_next_state(
+        &self,
+        board: &Vec<Vec<i8>>,
+        player: i8,
+        action: usize,
+    ) -> (Vec<Vec<i8>>, i8) {
+        let mut next_board = board.clone();
+        let (next_player, _) = self.apply(&mut next_board, player, action);
+        (next_board, next_player)
+    }
+
+    fn apply(&self, board: &mut Vec<Vec<i8>>, player: i8, action: usize) -> (i8, (usize, usize)) {
+        let row = self
+            .landing_row(board, action)
+            .expect("the column should not be full");
+        board[row][action] = player;
+        (-player, (row, action))
+    }
+
+    fn undo(&self, board: &mut Vec<Vec<i8>>, undo: &(usize, usize)) {
+        board[undo.0][undo.1] = 0;
+    }
+
+    fn get_valid_moves(&self, board: &Vec<Vec<i8>>, _player: i8) -> Vec<u8> {
+        board[0].iter().map(|square| (*square == 0) as u8).collect()
+    }
+
+    /// return None if not ended, otherwise the result for player: a win or a
+    /// loss by a margin of 1 when either player has four in a row, or a draw
+    /// when the board is full
+    fn get_game_ended(&self, board: &Vec<Vec<i8>>, player: i8) -> Option<GameResult> {
+        if has_line(board, player, Self::WIN_LENGTH) {
+            return Some(GameResult::from_margin(1));
+        }
+        if has_line(board, -player, Self::WIN_LENGTH) {
+            return Some(GameResult::from_margin(-1));
+        }
+        if board[0].iter().all(|square| *square != 0) {
+            return Some(GameResult::from_margin(0));
+        }
+        None
+    }
+
+    fn get_canonical_form(&self, board: &Vec<Vec<i8>>, player: i8) -> Vec<Vec<i8>> {
+        board
+            .iter()
+            .map(|row| row.iter().map(|square| square * player).collect())
+            .collect()
+    }
+
+    /// Returns the board and its horizontal mirror image. Gravity rules out
+    /// every other symmetry.
+    fn get_symmetries(&self, board: &Vec<Vec<i8>>, pi: &[f32]) -> Vec<(Vec<Vec<i8>>, Vec<f32>)> {
+        let mirror = board
+            .iter()
+            .map(|row| row.iter().rev().copied().collect())
+            .collect();
+        let mirror_pi = pi.iter().rev().copied().collect();
+        vec![(board.clone(), pi.to_vec()), (mirror, mirror_pi)]
+    }
+
+    fn string_representation(&self, board: &Vec<Vec<i8>>) -> String {
+        let mut sr = String::with_capacity(self.rows * self.cols);
+        for row in board {
+            for square in row {
+                sr.push_str(Self::square_content(*square));
+            }
+        }
+        sr
+    }
+
+    fn display(&self, board: &Vec<Vec<i8>>) {
+        for row in board {
+            print!("| ");
+            for square in row {
+                print!("{} ", Self::square_content(*square));
+            }
+            println!("|");
+        }
+        print!("  ");
+        for y in 0..self.cols {
+            print!("{} ", (b'a' + y as u8) as char);
+        }
+        println!();
+    }
+
+    /// One plane with 1 for the discs of player 1, -1 for those of player -1
+    /// and 0 for empty squares.
+    fn encode(&self, board: &Vec<Vec<i8>>) -> Vec<f32> {
+        board
+            .iter()
+            .flatten()
+            .map(|square| *square as f32)
+            .collect()
+    }
+
+    /// Columns are written as letters from "a".
+    fn action_to_string(&self, action: usize) -> String {
+        ((b'a' + action as u8) as char).to_string()
+    }
+
+    fn string_to_action(&self, s: &str) -> Option<usize> {
+        let s = s.trim().to_ascii_lowercase();
+        let mut chars = s.chars();
+        let col = chars.next()?;
+        if !col.is_ascii_lowercase() || chars.next().is_some() {
+            return None;
+        }
+        let col = (col as u8 - b'a') as usize;
+        if col >= self.cols {
+            return None;
+        }
+        Some(col)
+    }
+
+    fn zobrist_hash(&self, board: &Vec<Vec<i8>>) -> u64 {
+        let mut black = 0u64;
+        let mut white = 0u64;
+        for (i, square) in board.iter().flatten().enumerate() {
+            match square {
+                1 => black |= 1 << i,
+                -1 => white |= 1 << i,
+                _ => {}
+            }
+        }
+        self.zobrist.hash_bits(black, 1) ^ self.zobrist.hash_bits(white, -1)
+    }
+
+    fn get_next_state_hashed(
+        &self,
+        board: &Vec<Vec<i8>>,
+        player: i8,
+        action: usize,
+        hash: u64,
+    ) -> (Vec<Vec<i8>>, i8, u64) {
+        let mut next_board = board.clone();
+        let (next_player, (row, col)) = self.apply(&mut next_board, player, action);
+        let hash = hash ^ self.zobrist.hash_bits(1 << (row * self.cols + col), player);
+        (next_board, next_player, hash)
+    }
+
+    fn get_canonical_hash(&self, hash: u64, player: i8) -> u64 {
+        Zobrist::canonical(hash, player)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,244 @@
+use std::{collections::HashMap, env, fs, process};
+
+use burn::backend::{Autodiff, LibTorch};
+use rand::Rng;
+
+use super::ConnectFour;
+use crate::{
+    arena::Arena,
+    coach::Coach,
+    game::{conformance, Game, GameResult},
+    game_record::{self, GameRecord},
+    n_net::NNetWrapper,
+    othello_neural_net::ModelConfig,
+};
+
+/// Plays the columns in order from the initial board, starting with player 1.
+fn play(game: &ConnectFour, cols: &[usize]) -> (Vec<Vec<i8>>, i8) {
+    let mut board = game.get_init_board().clone();
+    let mut player = 1;
+    for col in cols {
+        (board, player) = game.get_next_state(&board, player, *col);
+    }
+    (board, player)
+}
+
+fn random_action(game: &ConnectFour, board: &Vec<Vec<i8>>, player: i8) -> usize {
+    let valids = game.get_valid_moves(board, player);
+    let legal: Vec<usize> = (0..valids.len()).filter(|a| valids[*a] == 1).collect();
+    legal[rand::thread_rng().gen_range(0..legal.len())]
+}
+
+#[test]
+fn init_board_6() {
+    let game = ConnectFour::new(6);
+    assert_eq!(game.get_board_size(), (6, 7));
+    assert_eq!(game.get_action_size(), 7);
+    let board = game.get_init_board();
+    assert_eq!(game.get_valid_moves(board, 1), vec![1; 7]);
+    assert_eq!(game.get_game_ended(board, 1), None);
+}
+
+#[test]
+fn discs_fall_to_the_bottom() {
+    let game = ConnectFour::new(6);
+    let (board, player) = play(&game, &[3, 3, 4]);
+    assert_eq!(player, -1);
+    assert_eq!(board[5][3], 1);
+    assert_eq!(board[4][3], -1);
+    assert_eq!(board[5][4], 1);
+    assert_eq!(board[3][3], 0);
+}
+
+#[test]
+fn full_column_is_not_valid() {
+    let game = ConnectFour::new(6);
+    let (board, _) = play(&game, &[0, 0, 0, 0, 0, 0]);
+    assert_eq!(game.get_valid_moves(&board, 1), vec![0, 1, 1, 1, 1, 1, 1]);
+}
+
+#[test]
+fn wins() {
+    let game = ConnectFour::new(6);
+    let horizontal = [0, 0, 1, 1, 2, 2, 3];
+    let vertical = [0, 1, 0, 1, 0, 1, 0];
+    let diagonal = [0, 1, 1, 2, 2, 3, 2, 3, 3, 6, 3];
+    let anti_diagonal = [6, 5, 5, 4, 4, 3, 4, 3, 3, 0, 3];
+    for cols in [&horizontal[..], &vertical, &diagonal, &anti_diagonal] {
+        let (board, player) = play(&game, &cols[..cols.len() - 1]);
+        assert_eq!(game.get_game_ended(&board, player), None, "{cols:?}");
+        let (board, player) = play(&game, cols);
+        // player 1 made the last move and won, player -1 is to move
+        assert_eq!(player, -1);
+        assert_eq!(
+            game.get_game_ended(&board, player),
+            Some(GameResult::from_margin(-1)),
+            "{cols:?}"
+        );
+        assert_eq!(
+            game.get_game_ended(&board, -player),
+            Some(GameResult::from_margin(1))
+        );
+    }
+}
+
+#[test]
+fn full_board_is_a_draw() {
+    let game = ConnectFour::new(6);
+    // columns alternate colors, and the pattern is inverted every
+    // two rows, so that no line of four appears
+    let board: Vec<Vec<i8>> = (0..6)
+        .map(|x| (0..7).map(|y| 1 - 2 * ((x / 2 + y) % 2) as i8).collect())
+        .collect();
+    assert_eq!(
+        game.get_game_ended(&board, 1),
+        Some(GameResult::from_margin(0))
+    );
+    assert_eq!(game.get_valid_moves(&board, 1), vec![0; 7]);
+}
+
+#[test]
+fn symmetries_mirror_columns() {
+    let game = ConnectFour::new(6);
+    let (board, player) = play(&game, &[0, 1, 1]);
+    let pi: Vec<f32> = (0..7).map(|a| a as f32).collect();
+    let symmetries = game.get_symmetries(&board, &pi);
+    assert_eq!(symmetries.len(), 2);
+    assert_eq!(symmetries[0], (board.clone(), pi.clone()));
+    let (mirror, mirror_pi) = &symmetries[1];
+    assert_eq!(mirror_pi, &vec![6., 5., 4., 3., 2., 1., 0.]);
+    // the mirrored board is reached by the mirrored moves
+    assert_eq!(mirror, &play(&game, &[6, 5, 5]).0);
+    assert_eq!(player, -1);
+}
+
+#[test]
+fn apply_undo_and_hashes() {
+    let game = ConnectFour::new(6);
+    for _ in 0..20 {
+        let mut board = game.get_init_board().clone();
+        let mut player = 1;
+        let mut hash = game.zobrist_hash(&board);
+        while game.get_game_ended(&board, player).is_none() {
+            let action = random_action(&game, &board, player);
+            let (next_board, next_player, next_hash) =
+                game.get_next_state_hashed(&board, player, action, hash);
+            assert_eq!(next_hash, game.zobrist_hash(&next_board));
+
+            let mut applied = board.clone();
+            let (applied_player, undo) = game.apply(&mut applied, player, action);
+            assert_eq!((&applied, applied_player), (&next_board, next_player));
+            game.undo(&mut applied, &undo);
+            assert_eq!(applied, board);
+
+            (board, player, hash) = (next_board, next_player, next_hash);
+        }
+    }
+}
+
+#[test]
+fn action_strings() {
+    let game = ConnectFour::new(6);
+    for action in 0..7 {
+        let s = game.action_to_string(action);
+        assert_eq!(game.string_to_action(&s), Some(action));
+        assert_eq!(game.string_to_action(&s.to_uppercase()), Some(action));
+    }
+    assert_eq!(game.action_to_string(3), "d");
+    for s in ["", "h", "a1", "pass"] {
+        assert_eq!(game.string_to_action(s), None, "{s}");
+    }
+}
+
+#[test]
+fn arena_random_players() {
+    let game = ConnectFour::new(6);
+    let player = |b: &Vec<Vec<i8>>| random_action(&game, b, 1);
+    let mut arena = Arena::new(player, player, &game, |b: &Vec<Vec<i8>>| game.display(b));
+    let (one_won, two_won, draws) = arena.play_games(20, false);
+    assert_eq!(one_won + two_won + draws, 20);
+
+    // player 1 completes column a before player 2 completes column b
+    let mut arena = Arena::new(
+        |_: &Vec<Vec<i8>>| 0,
+        |_: &Vec<Vec<i8>>| 1,
+        &game,
+        |_: &Vec<Vec<i8>>| {},
+    );
+    assert_eq!(
+        arena.play_game(game.get_init_board(), 1, false),
+        GameResult::from_margin(1)
+    );
+    assert_eq!(
+        arena.play_game(game.get_init_board(), -1, false),
+        GameResult::from_margin(-1)
+    );
+}
+
+#[test]
+fn conformance() {
+    conformance::check(&ConnectFour::new(6), 20);
+    conformance::check(&ConnectFour::new_rect(4, 5), 20);
+}
+
+#[test]
+fn coach_learns_connect_four() {
+    let game = ConnectFour::new(6);
+    let folder = env::temp_dir().join(format!("connect_four_learn_{}", process::id()));
+    let mut args = HashMap::new();
+    for (key, value) in [
+        ("numIters", "1"),
+        // enough examples for a batch of training
+        ("numEps", "3"),
+        ("tempThreshold", "15"),
+        ("updateThreshold", "0.6"),
+        ("maxlenOfQueue", "200000"),
+        ("numMCTSSims", "4"),
+        ("arenaCompare", "2"),
+        ("cpuct", "1"),
+        ("drawValue", "0"),
+        ("checkpoint", folder.to_str().unwrap()),
+        ("numItersForTrainExamplesHistory", "20"),
+        ("verbose", "false"),
+        ("saveGames", "true"),
+    ] {
+        args.insert(key.to_owned(), value.to_owned());
+    }
+    let config = ModelConfig::new(6, 7, game.get_action_size()).with_num_channels(8);
+    let nnet = NNetWrapper::<Autodiff<LibTorch>, ConnectFour>::with_config(
+        game.clone(),
+        Default::default(),
+        &config,
+    );
+    Coach::new(game, nnet, args).learn();
+
+    let games: Vec<GameRecord<Vec<Vec<i8>>>> =
+        game_record::load_records(&folder.join("games_1.bin")).unwrap();
+    assert_eq!(games.len(), 3);
+    assert!(games.iter().all(|g| g.result.is_some()));
+    let arena: Vec<GameRecord<Vec<Vec<i8>>>> =
+        game_record::load_records(&folder.join("arena_1.bin")).unwrap();
+    assert_eq!(arena.len(), 2);
+    // the examples of the iteration, and the network before training, which
+    // the recorder saves with its own extension
+    assert!(folder.join("checkpoint_0.pth.tar").exists());
+    assert!(folder.join("temp.pth.mpk").exists());
+    fs::remove_dir_all(folder).unwrap();
+}
+
+#[test]
+#[should_panic(expected = "the searches would never stop")]
+fn coach_rejects_searches_without_limits() {
+    let game = ConnectFour::new(6);
+    let mut args = HashMap::new();
+    for (key, value) in [("numMCTSSims", "0"), ("searchTimeMs", "0"), ("cpuct", "1")] {
+        args.insert(key.to_owned(), value.to_owned());
+    }
+    let config = ModelConfig::new(6, 7, game.get_action_size()).with_num_channels(8);
+    let nnet = NNetWrapper::<Autodiff<LibTorch>, ConnectFour>::with_config(
+        game.clone(),
+        Default::default(),
+        &config,
+    );
+    Coach::new(game, nnet, args);
+}
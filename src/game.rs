@@ -1,4 +1,112 @@
-pub trait Game {
+use std::{collections::HashMap, fmt};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// Outcome of a finished game for one player.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Outcome {
+    Win,
+    Loss,
+    Draw,
+}
+
+/// How a game that was not played out ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Forfeit {
+    /// A player played an illegal action, and lost.
+    IllegalAction,
+    /// A player resigned, in imported games.
+    Resignation,
+    /// A player ran out of time, in imported games.
+    Time,
+    /// The players agreed on the score, in imported games.
+    AgreedScore,
+}
+
+/// Result of a finished game from the point of view of one player.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameResult {
+    pub outcome: Outcome,
+    /// Final score difference for the player. Zero for a draw or an illegal
+    /// action.
+    pub margin: i32,
+    /// How the game ended if it was not played out, None if it was.
+    pub forfeit: Option<Forfeit>,
+}
+
+impl GameResult {
+    pub fn from_margin(margin: i32) -> Self {
+        let outcome = if margin > 0 {
+            Outcome::Win
+        } else if margin < 0 {
+            Outcome::Loss
+        } else {
+            Outcome::Draw
+        };
+        GameResult {
+            outcome,
+            margin,
+            forfeit: None,
+        }
+    }
+
+    /// A loss for the player, who forfeited by playing an illegal action.
+    pub fn forfeit() -> Self {
+        GameResult {
+            outcome: Outcome::Loss,
+            margin: 0,
+            forfeit: Some(Forfeit::IllegalAction),
+        }
+    }
+
+    /// Returns the same result from the point of view of the opponent.
+    pub fn for_opponent(&self) -> Self {
+        let outcome = match self.outcome {
+            Outcome::Win => Outcome::Loss,
+            Outcome::Loss => Outcome::Win,
+            Outcome::Draw => Outcome::Draw,
+        };
+        GameResult {
+            outcome,
+            margin: -self.margin,
+            forfeit: self.forfeit,
+        }
+    }
+
+    /// Returns 1 for a win, -1 for a loss and draw_value for a draw.
+    pub fn value(&self, draw_value: f32) -> f32 {
+        match self.outcome {
+            Outcome::Win => 1.,
+            Outcome::Loss => -1.,
+            Outcome::Draw => draw_value,
+        }
+    }
+}
+
+/// Reason why an action cannot be played.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ActionError {
+    /// The action has no index below the action size.
+    OutOfRange(String),
+    /// The game has already ended.
+    GameOver,
+    /// The action is not a valid move for the player.
+    Illegal { action: String, player: i8 },
+}
+
+impl fmt::Display for ActionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ActionError::OutOfRange(action) => write!(f, "action {action} is out of range"),
+            ActionError::GameOver => write!(f, "the game is over"),
+            ActionError::Illegal { action, player } => {
+                write!(f, "{action} is not a valid move for player {player}")
+            }
+        }
+    }
+}
+
+pub trait Game: Sync {
     /// This class specifies the base Game class. To define your own game, subclass
     /// this class and implement the functions below. This works when the game is
     /// two-player, adversarial and turn-based.
@@ -8,19 +116,60 @@ pub trait Game {
     /// See othello/OthelloGame.py for an example implementation.
     fn new(n: usize) -> Self;
 
+    /// A board of the game. The player to move is passed alongside it.
+    /// Training examples are saved with their boards, hence the serde bounds,
+    /// and parallel searches send them between threads.
+    type State: Clone + PartialEq + fmt::Debug + Serialize + DeserializeOwned + Send;
+
+    /// A move of the game. Every action has an index in
+    /// 0..get_action_size(), which is its position in policy vectors and in
+    /// the vector of valid moves.
+    type Action: Copy + PartialEq + fmt::Debug;
+
+    /// Record returned by apply that lets undo restore the board.
+    type Undo;
+
     /// Returns:
     ///     startBoard: a representation of the board (ideally this is the form
     ///                 that will be the input to your neural network)
-    fn get_init_board(&self) -> &Vec<Vec<i8>>;
+    fn get_init_board(&self) -> &Self::State;
+
+    /// Returns:
+    ///     startBoard: the board to start a new game from, with player 1 to
+    ///                 move. Games that play from a set of openings return a
+    ///                 different one for every call, the others their initial
+    ///                 board.
+    fn get_start_board(&self) -> Self::State {
+        self.get_init_board().clone()
+    }
 
     /// Returns:
     ///     (x,y): a tuple of board dimensions
-    fn get_board_size(&self) -> (i8, i8);
+    fn get_board_size(&self) -> (usize, usize);
+
+    /// Returns:
+    ///     (x,y): the dimensions of board, which differ from get_board_size
+    ///            for a board of another size, e.g. one loaded from a record
+    fn get_state_size(&self, board: &Self::State) -> (usize, usize);
 
     /// Returns:
     ///     actionSize: number of all possible actions
     fn get_action_size(&self) -> usize;
 
+    /// Input:
+    ///     action: an action
+    ///
+    /// Returns:
+    ///     index: the index of action, in 0..get_action_size()
+    fn action_to_index(&self, action: Self::Action) -> usize;
+
+    /// Input:
+    ///     index: an index in 0..get_action_size()
+    ///
+    /// Returns:
+    ///     action: the action with that index
+    fn index_to_action(&self, index: usize) -> Self::Action;
+
     /// Input:
     ///     board: current board
     ///     player: current player (1 or -1)
@@ -29,7 +178,59 @@ pub trait Game {
     /// Returns:
     ///     nextBoard: board after applying action
     ///     nextPlayer: player who plays in the next turn (should be -player)
-    fn get_next_state(&self, board: &Vec<Vec<i8>>, player: i8, action: u8) -> (Vec<Vec<i8>>, i8);
+    fn get_next_state(
+        &self,
+        board: &Self::State,
+        player: i8,
+        action: Self::Action,
+    ) -> (Self::State, i8);
+
+    /// Same as get_next_state, but checks action first. get_next_state
+    /// assumes a valid action and may corrupt the board otherwise.
+    ///
+    /// Returns:
+    ///     (nextBoard, nextPlayer): as get_next_state, or the reason why action
+    ///                              cannot be played on board by player
+    fn try_next_state(
+        &self,
+        board: &Self::State,
+        player: i8,
+        action: Self::Action,
+    ) -> Result<(Self::State, i8), ActionError> {
+        let index = self.action_to_index(action);
+        // an action off the board may still map to the index of another one
+        if index >= self.get_action_size() || self.index_to_action(index) != action {
+            return Err(ActionError::OutOfRange(format!("{action:?}")));
+        }
+        if self.get_game_ended(board, player).is_some() {
+            return Err(ActionError::GameOver);
+        }
+        if self.get_valid_moves(board, player)[index] == 0 {
+            return Err(ActionError::Illegal {
+                action: self.action_to_string(action),
+                player,
+            });
+        }
+        Ok(self.get_next_state(board, player, action))
+    }
+
+    /// Applies action to board in place, so that a search can walk a single
+    /// board instead of cloning it for every move.
+    ///
+    /// Input:
+    ///     board: current board, modified to the next board
+    ///     player: current player (1 or -1)
+    ///     action: action taken by current player
+    ///
+    /// Returns:
+    ///     nextPlayer: player who plays in the next turn (should be -player)
+    ///     undo: record that restores the current board when passed to undo
+    fn apply(&self, board: &mut Self::State, player: i8, action: Self::Action) -> (i8, Self::Undo);
+
+    /// Input:
+    ///     board: board after apply, modified back to the board before apply
+    ///     undo: record returned by apply
+    fn undo(&self, board: &mut Self::State, undo: &Self::Undo);
 
     /// Input:
     ///     board: current board
@@ -38,17 +239,17 @@ pub trait Game {
     /// Returns:
     ///     validMoves: a binary vector of length self.getActionSize(), 1 for
     ///                 moves that are valid from the current board and player,
-    ///                 0 for invalid moves
-    fn get_valid_moves(&self, board: &Vec<Vec<i8>>, player: i8) -> Vec<u8>;
+    ///                 0 for invalid moves, indexed by action_to_index
+    fn get_valid_moves(&self, board: &Self::State, player: i8) -> Vec<u8>;
 
     /// Input:
     ///     board: current board
     ///     player: current player (1 or -1)
     ///
     /// Returns:
-    ///     r: 0 if game has not ended. 1 if player won, -1 if player lost,
-    ///        small non-zero value for draw.
-    fn get_game_ended(&self, board: &Vec<Vec<i8>>, player: i8) -> i8;
+    ///     r: None if game has not ended. Otherwise the result for player:
+    ///        a win, loss or draw together with the final margin.
+    fn get_game_ended(&self, board: &Self::State, player: i8) -> Option<GameResult>;
 
     /// Input:
     ///     board: current board
@@ -61,7 +262,7 @@ pub trait Game {
     ///                     of white. When the player is white, we can return
     ///                     board as is. When the player is black, we can invert
     ///                     the colors and return the board.
-    fn get_canonical_form(&self, board: &Vec<Vec<i8>>, player: i8) -> Vec<Vec<i8>>;
+    fn get_canonical_form(&self, board: &Self::State, player: i8) -> Self::State;
 
     /// Input:
     ///     board: current board
@@ -71,7 +272,45 @@ pub trait Game {
     ///     symmForms: a list of [(board,pi)] where each tuple is a symmetrical
     ///                     form of the board and the corresponding pi vector. This
     ///                     is used when training the neural network from examples.
-    fn get_symmetries(&self, board: &Vec<Vec<i8>>, pi: &Vec<f32>) -> Vec<(Vec<Vec<i8>>, Vec<f32>)>;
+    ///                     The first form is board itself, and the forms come
+    ///                     in the same order for every board.
+    fn get_symmetries(&self, board: &Self::State, pi: &[f32]) -> Vec<(Self::State, Vec<f32>)>;
+
+    /// Input:
+    ///     board: current board
+    ///
+    /// Returns:
+    ///     (symmBoard, symmetry): the representative of the symmetrical forms
+    ///                            of board, which is the same for all of them,
+    ///                            and its index in get_symmetries(board). The
+    ///                            default picks the least string_representation.
+    fn get_canonical_symmetry(&self, board: &Self::State) -> (Self::State, usize) {
+        let pi = vec![0.; self.get_action_size()];
+        self.get_symmetries(board, &pi)
+            .into_iter()
+            .enumerate()
+            .min_by_key(|(_, (b, _))| self.string_representation(b))
+            .map(|(i, (b, _))| (b, i))
+            .expect("get_symmetries returns at least the board itself")
+    }
+
+    /// Input:
+    ///     symmetry: index of a form in get_symmetries
+    ///     pi: policy vector of that form
+    ///
+    /// Returns:
+    ///     pi: the policy vector mapped back onto the board the form was made
+    ///         from
+    fn policy_from_symmetry(&self, symmetry: usize, pi: &[f32]) -> Vec<f32> {
+        // symmetries map policies the same way on every board
+        let indices: Vec<f32> = (0..pi.len()).map(|a| a as f32).collect();
+        let (_, origin) = &self.get_symmetries(self.get_init_board(), &indices)[symmetry];
+        let mut original = vec![0.; pi.len()];
+        for (p, a) in pi.iter().zip(origin) {
+            original[*a as usize] = *p;
+        }
+        original
+    }
 
     /// Input:
     ///     board: current board
@@ -79,5 +318,109 @@ pub trait Game {
     /// Returns:
     ///     boardString: a quick conversion of board to a string format.
     ///                  Required by MCTS for hashing.
-    fn string_representation(&self, board: &Vec<Vec<i8>>) -> String;
+    fn string_representation(&self, board: &Self::State) -> String;
+
+    /// Prints board to the terminal.
+    fn display(&self, board: &Self::State);
+
+    /// Input:
+    ///     board: current board in its canonical form
+    ///
+    /// Returns:
+    ///     input: the input of the neural network for board, a plane of
+    ///            get_board_size() values in row-major order
+    fn encode(&self, board: &Self::State) -> Vec<f32>;
+
+    /// Input:
+    ///     action: an action
+    ///
+    /// Returns:
+    ///     moveString: the action in standard notation, e.g. "f5" or "pass"
+    fn action_to_string(&self, action: Self::Action) -> String;
+
+    /// Input:
+    ///     s: an action in standard notation
+    ///
+    /// Returns:
+    ///     action: the action, or None if s is not an action of this game
+    fn string_to_action(&self, s: &str) -> Option<Self::Action>;
+
+    /// Input:
+    ///     board: current board
+    ///
+    /// Returns:
+    ///     hash: a 64-bit Zobrist hash of the board. Used by MCTS to key its
+    ///           tables.
+    fn zobrist_hash(&self, board: &Self::State) -> u64;
+
+    /// Same as get_next_state, but also updates the Zobrist hash of board
+    /// incrementally.
+    ///
+    /// Input:
+    ///     board: current board
+    ///     player: current player (1 or -1)
+    ///     action: action taken by current player
+    ///     hash: zobrist_hash of board
+    ///
+    /// Returns:
+    ///     nextBoard: board after applying action
+    ///     nextPlayer: player who plays in the next turn (should be -player)
+    ///     nextHash: zobrist_hash of nextBoard
+    fn get_next_state_hashed(
+        &self,
+        board: &Self::State,
+        player: i8,
+        action: Self::Action,
+        hash: u64,
+    ) -> (Self::State, i8, u64);
+
+    /// Input:
+    ///     hash: zobrist_hash of the current board
+    ///     player: current player (1 or -1)
+    ///
+    /// Returns:
+    ///     canonicalHash: zobrist_hash of get_canonical_form(board, player)
+    fn get_canonical_hash(&self, hash: u64, player: i8) -> u64;
 }
+
+/// A training example: a board in its canonical form, the policy of the
+/// search (pi) and the value of the game for the player to move (v).
+pub type Example<G> = (<G as Game>::State, Vec<f32>, f32);
+
+/// Merges the examples of boards that are symmetrical forms of each other,
+/// averaging their policies (mapped onto the canonical symmetry) and values.
+/// Every merged example is returned with all its symmetrical forms, as
+/// self-play returns them.
+pub fn deduplicate_examples<G: Game>(game: &G, examples: &[Example<G>]) -> Vec<Example<G>> {
+    let mut index = HashMap::new();
+    // (canonical board, sum of policies, sum of values, count)
+    let mut merged: Vec<(G::State, Vec<f32>, f32, usize)> = Vec::new();
+    for (board, pi, v) in examples {
+        let (canonical, symmetry) = game.get_canonical_symmetry(board);
+        let (_, canonical_pi) = game.get_symmetries(board, pi).swap_remove(symmetry);
+        let i = *index
+            .entry(game.string_representation(&canonical))
+            .or_insert_with(|| {
+                merged.push((canonical, vec![0.; pi.len()], 0., 0));
+                merged.len() - 1
+            });
+        let (_, pi_sum, v_sum, count) = &mut merged[i];
+        for (s, p) in pi_sum.iter_mut().zip(canonical_pi) {
+            *s += p;
+        }
+        *v_sum += v;
+        *count += 1;
+    }
+    let mut deduplicated = Vec::new();
+    for (board, pi_sum, v_sum, count) in merged {
+        let pi: Vec<f32> = pi_sum.iter().map(|p| p / count as f32).collect();
+        let v = v_sum / count as f32;
+        for (b, p) in game.get_symmetries(&board, &pi) {
+            deduplicated.push((b, p, v));
+        }
+    }
+    deduplicated
+}
+
+#[cfg(test)]
+pub mod conformance;
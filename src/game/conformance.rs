@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+
+use rand::Rng;
+
+use crate::game::Game;
+
+/// Checks the properties every Game implementation should have on the
+/// states reached by random playouts from get_start_board:
+///
+/// - every symmetry maps the valid moves, the next states and the result of
+///   a board onto those of the transformed board
+/// - get_canonical_symmetry picks the same form for every symmetry of a
+///   board, and policy_from_symmetry undoes the symmetry it reports
+/// - get_canonical_form is an involution
+/// - non-terminal states have at least one valid move
+/// - string_representation is injective
+///
+/// Panics with the offending board on the first violation.
+pub fn check<G: Game>(game: &G, playouts: usize) {
+    let mut representations = HashMap::new();
+    let mut rng = rand::thread_rng();
+    for _ in 0..playouts {
+        let mut board = game.get_start_board();
+        let mut player = 1;
+        loop {
+            check_canonical_form(game, &board, player);
+            check_symmetries(game, &game.get_canonical_form(&board, player));
+            check_canonical_symmetry(game, &board);
+            check_representation(game, &mut representations, &board);
+            if game.get_game_ended(&board, player).is_some() {
+                break;
+            }
+            let valids = game.get_valid_moves(&board, player);
+            let legal: Vec<usize> = (0..valids.len()).filter(|a| valids[*a] == 1).collect();
+            assert!(
+                !legal.is_empty(),
+                "no valid move for {player} in the unfinished game {}",
+                game.string_representation(&board)
+            );
+            let action = game.index_to_action(legal[rng.gen_range(0..legal.len())]);
+            (board, player) = game.get_next_state(&board, player, action);
+        }
+    }
+}
+
+fn check_canonical_form<G: Game>(game: &G, board: &G::State, player: i8) {
+    let sr = game.string_representation(board);
+    let canonical = game.get_canonical_form(board, player);
+    assert_eq!(
+        &game.get_canonical_form(&canonical, player),
+        board,
+        "canonical form of {sr} for {player} is not an involution"
+    );
+    assert_eq!(
+        &game.get_canonical_form(board, 1),
+        board,
+        "canonical form of {sr} for 1 changes the board"
+    );
+    assert_eq!(
+        game.get_valid_moves(&canonical, 1),
+        game.get_valid_moves(board, player),
+        "canonical form of {sr} for {player} changes the valid moves"
+    );
+    assert_eq!(
+        game.get_game_ended(&canonical, 1),
+        game.get_game_ended(board, player),
+        "canonical form of {sr} for {player} changes the result"
+    );
+}
+
+/// Each symmetry is identified by the policy it returns for the policy of
+/// action indices, which maps every action of the transformed board to the
+/// action of board it came from.
+fn check_symmetries<G: Game>(game: &G, board: &G::State) {
+    let sr = game.string_representation(board);
+    let action_size = game.get_action_size();
+    let indices: Vec<f32> = (0..action_size).map(|a| a as f32).collect();
+    let symmetries = game.get_symmetries(board, &indices);
+    assert_eq!(
+        symmetries.first(),
+        Some(&(board.clone(), indices.clone())),
+        "the first symmetry of {sr} is not the identity"
+    );
+
+    let valids = game.get_valid_moves(board, 1);
+    let result = game.get_game_ended(board, 1);
+    for (i, (sym_board, sym_pi)) in symmetries.iter().enumerate() {
+        let sym_sr = game.string_representation(sym_board);
+        let origin: Vec<usize> = sym_pi.iter().map(|a| *a as usize).collect();
+        let mut sorted = origin.clone();
+        sorted.sort();
+        assert_eq!(
+            sorted,
+            (0..action_size).collect::<Vec<usize>>(),
+            "symmetry {i} of {sr} does not permute the actions"
+        );
+        assert_eq!(
+            game.get_game_ended(sym_board, 1),
+            result,
+            "symmetry {i} of {sr} changes the result"
+        );
+
+        let sym_valids = game.get_valid_moves(sym_board, 1);
+        for a in 0..action_size {
+            assert_eq!(
+                sym_valids[a], valids[origin[a]],
+                "symmetry {i} of {sr} maps valid moves inconsistently"
+            );
+            if sym_valids[a] == 0 || result.is_some() {
+                continue;
+            }
+            // the same symmetry relates the next states
+            let (next, next_player) =
+                game.get_next_state(board, 1, game.index_to_action(origin[a]));
+            let (sym_next, sym_next_player) =
+                game.get_next_state(sym_board, 1, game.index_to_action(a));
+            assert_eq!(sym_next_player, next_player);
+            assert_eq!(
+                game.get_symmetries(&next, &indices)[i].0,
+                sym_next,
+                "symmetry {i} of {sr} does not commute with {} on {sym_sr}",
+                game.action_to_string(game.index_to_action(a))
+            );
+        }
+    }
+}
+
+fn check_canonical_symmetry<G: Game>(game: &G, board: &G::State) {
+    let sr = game.string_representation(board);
+    let action_size = game.get_action_size();
+    let indices: Vec<f32> = (0..action_size).map(|a| a as f32).collect();
+    let symmetries = game.get_symmetries(board, &indices);
+    let (canonical, symmetry) = game.get_canonical_symmetry(board);
+    let (sym_board, sym_pi) = &symmetries[symmetry];
+    assert_eq!(
+        &canonical, sym_board,
+        "the canonical symmetry of {sr} is not symmetry {symmetry}"
+    );
+    assert_eq!(
+        game.policy_from_symmetry(symmetry, sym_pi),
+        indices,
+        "policy_from_symmetry does not undo symmetry {symmetry} of {sr}"
+    );
+    for (i, (sym_board, _)) in symmetries.iter().enumerate() {
+        assert_eq!(
+            game.get_canonical_symmetry(sym_board).0,
+            canonical,
+            "symmetry {i} of {sr} has another canonical symmetry"
+        );
+    }
+}
+
+fn check_representation<G: Game>(
+    game: &G,
+    representations: &mut HashMap<String, G::State>,
+    board: &G::State,
+) {
+    let sr = game.string_representation(board);
+    if let Some(other) = representations.get(&sr) {
+        assert_eq!(other, board, "two boards are represented as {sr}");
+    } else {
+        representations.insert(sr, board.clone());
+    }
+}
@@ -0,0 +1,343 @@
+use std::{collections::VecDeque, fmt, fs, io, path::Path};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    board::Board,
+    game::{Game, GameResult},
+    ggf,
+    notation::NotationError,
+    othello::Othello,
+};
+
+#[derive(Debug)]
+pub enum RecordError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    Binary(bincode::Error),
+    /// The start board is not of the size of the game the record is replayed
+    /// with.
+    SizeMismatch {
+        expected: (usize, usize),
+        found: (usize, usize),
+    },
+    /// A move could not be replayed.
+    Notation(NotationError),
+    /// The replayed game does not end with the recorded result.
+    ResultMismatch {
+        recorded: Option<GameResult>,
+        replayed: Option<GameResult>,
+    },
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordError::Io(e) => write!(f, "{e}"),
+            RecordError::Json(e) => write!(f, "invalid JSON record: {e}"),
+            RecordError::Binary(e) => write!(f, "invalid binary record: {e}"),
+            RecordError::SizeMismatch { expected, found } => write!(
+                f,
+                "the game is played on a {}x{} board, not {}x{}",
+                found.0, found.1, expected.0, expected.1
+            ),
+            RecordError::Notation(e) => write!(f, "{e}"),
+            RecordError::ResultMismatch { recorded, replayed } => write!(
+                f,
+                "the game was recorded as {recorded:?} but replays to {replayed:?}"
+            ),
+        }
+    }
+}
+
+/// A move of a recorded game, with the search statistics of the player that
+/// made it when they are known.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MoveRecord {
+    /// The action in standard notation.
+    pub action: String,
+    /// (action index, visits) of every action the search visited.
+    pub visits: Vec<(usize, u32)>,
+    /// Value of the position for the player to move, as estimated by the
+    /// search.
+    pub value: Option<f32>,
+    /// Time taken by the move in milliseconds.
+    pub time_ms: Option<f32>,
+}
+
+impl MoveRecord {
+    pub fn new(action: String) -> Self {
+        MoveRecord {
+            action,
+            visits: Vec::new(),
+            value: None,
+            time_ms: None,
+        }
+    }
+
+    /// Keeps the actions of counts, indexed by action index, that were visited.
+    pub fn with_visits(mut self, counts: &[u32]) -> Self {
+        self.visits = counts
+            .iter()
+            .enumerate()
+            .filter(|(_, n)| **n > 0)
+            .map(|(a, n)| (a, *n))
+            .collect();
+        self
+    }
+
+    pub fn with_value(mut self, value: Option<f32>) -> Self {
+        self.value = value;
+        self
+    }
+
+    pub fn with_time_ms(mut self, time_ms: f32) -> Self {
+        self.time_ms = Some(time_ms);
+        self
+    }
+}
+
+/// A complete game: the start position, every move including passes, the
+/// names of the players and the result. Records can be replayed through
+/// their Game, and are stored as JSON or in a compact binary format, see
+/// save_records.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GameRecord<S> {
+    /// Names of the players of color 1 and -1.
+    pub players: [String; 2],
+    pub start_board: S,
+    pub start_player: i8,
+    pub moves: Vec<MoveRecord>,
+    /// The result for the player of color 1, or None for an unfinished game.
+    pub result: Option<GameResult>,
+}
+
+impl<S: Clone + PartialEq + Serialize + DeserializeOwned> GameRecord<S> {
+    pub fn new(start_board: S, start_player: i8) -> Self {
+        GameRecord {
+            players: ["player1".to_owned(), "player2".to_owned()],
+            start_board,
+            start_player,
+            moves: Vec::new(),
+            result: None,
+        }
+    }
+
+    pub fn with_players(mut self, player1: &str, player2: &str) -> Self {
+        self.players = [player1.to_owned(), player2.to_owned()];
+        self
+    }
+
+    /// Replays the moves from the start board, checking its size, each of the
+    /// moves and the recorded result. The result of a game that was not played out is not
+    /// checked, as it ended before its moves did, or by an illegal action
+    /// that is not recorded.
+    ///
+    /// Returns:
+    ///     positions: every (board, player to move) of the game, starting with
+    ///                the start board
+    pub fn replay<G: Game<State = S>>(&self, game: &G) -> Result<Vec<(S, i8)>, RecordError> {
+        let (expected, found) = (
+            game.get_board_size(),
+            game.get_state_size(&self.start_board),
+        );
+        if found != expected {
+            return Err(RecordError::SizeMismatch { expected, found });
+        }
+        let mut board = self.start_board.clone();
+        let mut player = self.start_player;
+        let mut positions = vec![(board.clone(), player)];
+        for m in &self.moves {
+            let action = game.string_to_action(&m.action).ok_or_else(|| {
+                RecordError::Notation(NotationError::InvalidMove(m.action.clone()))
+            })?;
+            (board, player) = game.try_next_state(&board, player, action).map_err(|_| {
+                RecordError::Notation(NotationError::IllegalMove {
+                    ply: positions.len(),
+                    action: m.action.clone(),
+                })
+            })?;
+            positions.push((board.clone(), player));
+        }
+        if self.result.and_then(|r| r.forfeit).is_none() {
+            let replayed = game.get_game_ended(&board, player).map(|r| {
+                if player == 1 {
+                    r
+                } else {
+                    r.for_opponent()
+                }
+            });
+            if replayed != self.result {
+                return Err(RecordError::ResultMismatch {
+                    recorded: self.result,
+                    replayed,
+                });
+            }
+        }
+        Ok(positions)
+    }
+
+    /// Returns the training examples of the game, as Coach collects them in
+    /// self-play: every symmetry of the canonical board of each move, with the
+    /// visit counts as policy and the result for the player to move as value.
+    /// Moves without visits use the played action as policy.
+    pub fn training_examples<G: Game<State = S>>(
+        &self,
+        game: &G,
+        draw_value: f32,
+    ) -> Result<Vec<(S, Vec<f32>, f32)>, RecordError> {
+        let positions = self.replay(game)?;
+        let Some(result) = self.result else {
+            return Ok(Vec::new());
+        };
+        let mut examples = Vec::new();
+        for (m, (board, player)) in self.moves.iter().zip(positions) {
+            let mut pi = vec![0.; game.get_action_size()];
+            if m.visits.is_empty() {
+                // replay has checked the action
+                let action = game.string_to_action(&m.action).unwrap();
+                pi[game.action_to_index(action)] = 1.;
+            } else {
+                let total: u32 = m.visits.iter().map(|(_, n)| n).sum();
+                for (a, n) in &m.visits {
+                    pi[*a] = *n as f32 / total as f32;
+                }
+            }
+            let value = if player == 1 {
+                result
+            } else {
+                result.for_opponent()
+            }
+            .value(draw_value);
+            let canonical_board = game.get_canonical_form(&board, player);
+            for (b, p) in game.get_symmetries(&canonical_board, &pi) {
+                examples.push((b, p, value));
+            }
+        }
+        Ok(examples)
+    }
+}
+
+/// Returns records as JSON.
+pub fn to_json<S: Serialize>(records: &[GameRecord<S>]) -> String {
+    serde_json::to_string(records).expect("records should serialize to JSON")
+}
+
+pub fn from_json<S: DeserializeOwned>(json: &str) -> Result<Vec<GameRecord<S>>, RecordError> {
+    serde_json::from_str(json).map_err(RecordError::Json)
+}
+
+/// Returns records in the binary format, which stores the moves without
+/// field names and is much smaller than JSON.
+pub fn to_bytes<S: Serialize>(records: &[GameRecord<S>]) -> Vec<u8> {
+    bincode::serialize(records).expect("records should serialize to bincode")
+}
+
+pub fn from_bytes<S: DeserializeOwned>(bytes: &[u8]) -> Result<Vec<GameRecord<S>>, RecordError> {
+    bincode::deserialize(bytes).map_err(RecordError::Binary)
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == "json")
+}
+
+/// Writes records to path, as JSON when its extension is "json" and in the
+/// binary format otherwise.
+pub fn save_records<S: Serialize>(
+    path: &Path,
+    records: &[GameRecord<S>],
+) -> Result<(), RecordError> {
+    let bytes = if is_json(path) {
+        to_json(records).into_bytes()
+    } else {
+        to_bytes(records)
+    };
+    fs::write(path, bytes).map_err(RecordError::Io)
+}
+
+/// Reads records written by save_records.
+pub fn load_records<S: DeserializeOwned>(path: &Path) -> Result<Vec<GameRecord<S>>, RecordError> {
+    let bytes = fs::read(path).map_err(RecordError::Io)?;
+    if is_json(path) {
+        from_json(&String::from_utf8_lossy(&bytes))
+    } else {
+        from_bytes(&bytes)
+    }
+}
+
+/// Saves imported games of game to output: as records (see save_records), or
+/// with examples as the training examples of the finished games, in the
+/// format Coach loads with load_examples_file, where draws are worth
+/// draw_value as drawValue sets for self-play.
+pub fn save_imported<G: Game>(
+    game: &G,
+    records: &[GameRecord<G::State>],
+    output: &str,
+    examples: bool,
+    draw_value: f32,
+) {
+    if !examples {
+        match save_records(Path::new(output), records) {
+            Ok(()) => println!("Saved {} games to {output}", records.len()),
+            Err(e) => println!("Could not save {output}: {e}"),
+        }
+        return;
+    }
+    let mut examples = Vec::new();
+    for (i, record) in records.iter().enumerate() {
+        match record.training_examples(game, draw_value) {
+            Ok(e) => examples.extend(e),
+            Err(e) => println!("Skipping game {i}: {e}"),
+        }
+    }
+    let history = VecDeque::from([examples]);
+    let serialized = serde_pickle::to_vec(&history, Default::default()).unwrap();
+    match fs::write(output, serialized) {
+        Ok(()) => println!("Saved {} examples to {output}", history[0].len()),
+        Err(e) => println!("Could not save {output}: {e}"),
+    }
+}
+
+/// Runs `othello games <file> [board size] [json | ggf]`, replaying the
+/// Othello games saved in file and printing their transcripts, or the games
+/// as JSON or GGF.
+pub fn command(args: &[String]) {
+    let path = Path::new(
+        args.first()
+            .expect("Usage: othello games <file> [board size] [json | ggf]"),
+    );
+    let (game, _, _) = match Othello::from_command_arg(args.get(1).map_or("8", |v| v.as_str())) {
+        Ok(position) => position,
+        Err(e) => return println!("Invalid board size: {e}"),
+    };
+    let records: Vec<GameRecord<Board>> = match load_records(path) {
+        Ok(records) => records,
+        Err(e) => return println!("Could not load {}: {e}", path.display()),
+    };
+    match args.get(2).map(|v| v.as_str()) {
+        Some("json") => return println!("{}", to_json(&records)),
+        Some("ggf") => {
+            for record in &records {
+                println!("{}", ggf::to_ggf(record));
+            }
+            return;
+        }
+        _ => {}
+    }
+    for (i, record) in records.iter().enumerate() {
+        let [player1, player2] = &record.players;
+        match record.replay(&game) {
+            Ok(_) => {
+                let actions: Vec<String> = record.moves.iter().map(|m| m.action.clone()).collect();
+                let result = record.result.map_or("unfinished".to_owned(), |r| {
+                    format!("{:?} by {}", r.outcome, r.margin)
+                });
+                println!("{i}: {player1} - {player2} {result}: {}", actions.join(" "));
+            }
+            Err(e) => println!("{i}: {player1} - {player2}: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,207 @@
+use std::{collections::VecDeque, env, fs};
+
+use rand::Rng;
+
+use super::{
+    from_bytes, from_json, load_records, save_imported, save_records, to_bytes, to_json,
+    GameRecord, MoveRecord, RecordError,
+};
+use crate::{
+    arena::Arena,
+    board::Board,
+    game::{Example, Game, GameResult},
+    mnk_game::MnkGame,
+    notation::NotationError,
+    othello::Othello,
+};
+
+fn random_action<G: Game>(game: &G, board: &G::State) -> G::Action {
+    let valids = game.get_valid_moves(board, 1);
+    let legal: Vec<usize> = (0..valids.len()).filter(|a| valids[*a] == 1).collect();
+    game.index_to_action(legal[rand::thread_rng().gen_range(0..legal.len())])
+}
+
+/// Records of random Othello games, half of them started by player -1.
+fn othello_records(n: usize, games: usize) -> (Othello, Vec<GameRecord<Board>>) {
+    let othello = Othello::new(n);
+    let player = |b: &Board| random_action(&othello, b);
+    let mut arena = Arena::new(player, player, &othello, |_: &Board| {}).with_names("one", "two");
+    arena.play_games(games, false);
+    let records = arena.take_records();
+    (othello.clone(), records)
+}
+
+#[test]
+fn arena_records_replay() {
+    let (othello, records) = othello_records(6, 10);
+    assert_eq!(records.len(), 10);
+    for record in &records {
+        assert_eq!(record.players, ["one".to_owned(), "two".to_owned()]);
+        assert!(record.moves.iter().all(|m| m.time_ms.is_some()));
+        let positions = record.replay(&othello).unwrap();
+        assert_eq!(positions.len(), record.moves.len() + 1);
+        assert_eq!(positions[0], (record.start_board, record.start_player));
+        let (board, player) = positions.last().unwrap();
+        assert!(othello.get_game_ended(board, *player).is_some());
+    }
+    assert_eq!(records.iter().filter(|r| r.start_player == -1).count(), 5);
+}
+
+#[test]
+fn json_and_binary_round_trip() {
+    let (_, records) = othello_records(8, 2);
+    let json = to_json(&records);
+    assert_eq!(from_json::<Board>(&json).unwrap(), records);
+    let bytes = to_bytes(&records);
+    assert_eq!(from_bytes::<Board>(&bytes).unwrap(), records);
+    assert!(bytes.len() < json.len());
+    assert!(matches!(
+        from_json::<Board>("{}"),
+        Err(RecordError::Json(_))
+    ));
+    assert!(matches!(
+        from_bytes::<Board>(&[1, 2, 3]),
+        Err(RecordError::Binary(_))
+    ));
+}
+
+#[test]
+fn invalid_boards_are_record_errors() {
+    for pieces in [
+        vec![],
+        vec![vec![0; 4], vec![0; 3], vec![0; 4], vec![0; 4]],
+        vec![vec![0; 10]; 8],
+        vec![vec![0, 0], vec![0, 3]],
+    ] {
+        let records = [GameRecord::new(pieces, 1)];
+        assert!(matches!(
+            from_json::<Board>(&to_json(&records)),
+            Err(RecordError::Json(_))
+        ));
+        assert!(matches!(
+            from_bytes::<Board>(&to_bytes(&records)),
+            Err(RecordError::Binary(_))
+        ));
+    }
+}
+
+#[test]
+fn save_and_load_records() {
+    let (_, records) = othello_records(6, 4);
+    for name in ["othello_records_test.json", "othello_records_test.bin"] {
+        let path = env::temp_dir().join(name);
+        save_records(&path, &records).unwrap();
+        let loaded: Vec<GameRecord<Board>> = load_records(&path).unwrap();
+        assert_eq!(loaded, records);
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn replay_checks_moves_and_result() {
+    let (othello, records) = othello_records(6, 2);
+    let mut record = records[0].clone();
+    record.moves[1].action = record.moves[0].action.clone();
+    assert!(matches!(
+        record.replay(&othello),
+        Err(RecordError::Notation(NotationError::IllegalMove {
+            ply: 2,
+            ..
+        }))
+    ));
+    record.moves[1].action = "z9".to_owned();
+    assert!(matches!(
+        record.replay(&othello),
+        Err(RecordError::Notation(NotationError::InvalidMove(_)))
+    ));
+
+    let mut record = records[0].clone();
+    let result = record.result.unwrap();
+    record.result = Some(result.for_opponent());
+    if result.margin != 0 {
+        assert!(matches!(
+            record.replay(&othello),
+            Err(RecordError::ResultMismatch { .. })
+        ));
+    }
+    record.moves.pop();
+    record.result = None;
+    assert!(record.replay(&othello).is_ok());
+
+    assert!(matches!(
+        record.replay(&Othello::new(8)),
+        Err(RecordError::SizeMismatch {
+            expected: (8, 8),
+            found: (6, 6)
+        })
+    ));
+}
+
+#[test]
+fn forfeit_result_is_not_replayed() {
+    let game = MnkGame::new(3);
+    let mut arena = Arena::new(
+        |_: &Vec<Vec<i8>>| 0,
+        |_: &Vec<Vec<i8>>| 1,
+        &game,
+        |_: &Vec<Vec<i8>>| {},
+    );
+    arena.play_game(game.get_init_board(), 1, false);
+    let record = arena.take_records().pop().unwrap();
+    assert_eq!(record.moves.len(), 2);
+    assert_eq!(record.result, Some(GameResult::forfeit()));
+    assert_eq!(record.replay(&game).unwrap().len(), 3);
+}
+
+#[test]
+fn training_examples_from_visits() {
+    let game = MnkGame::new(3);
+    let mut record = GameRecord::new(game.get_init_board().clone(), 1);
+    // X wins on the first column, the first move with search statistics
+    for (i, action) in ["a1", "b1", "a2", "b2", "a3"].iter().enumerate() {
+        let mut m = MoveRecord::new(action.to_string());
+        if i == 0 {
+            m = m.with_visits(&[3, 1, 0, 0, 0, 0, 0, 0, 0]);
+        }
+        record.moves.push(m);
+    }
+    record.result = Some(GameResult::from_margin(1));
+
+    let examples = record.training_examples(&game, 0.).unwrap();
+    // eight symmetries of each of the five positions
+    assert_eq!(examples.len(), 40);
+    let (board, pi, value) = &examples[0];
+    assert_eq!(board, game.get_init_board());
+    assert_eq!(pi, &vec![0.75, 0.25, 0., 0., 0., 0., 0., 0., 0.]);
+    assert_eq!(*value, 1.);
+    // O to move on b1, in canonical form
+    let (board, pi, value) = &examples[8];
+    assert_eq!(board[0][0], -1);
+    assert_eq!(pi[1], 1.);
+    assert_eq!(*value, -1.);
+
+    // unfinished games teach nothing
+    record.moves.pop();
+    record.result = None;
+    assert!(record.training_examples(&game, 0.).unwrap().is_empty());
+}
+
+#[test]
+fn imported_draws_are_worth_the_draw_value() {
+    let game = MnkGame::new(3);
+    let mut record = GameRecord::new(game.get_init_board().clone(), 1);
+    for action in ["b2", "a1", "c1", "a3", "a2", "c2", "b1", "b3", "c3"] {
+        record.moves.push(MoveRecord::new(action.to_owned()));
+    }
+    record.result = Some(GameResult::from_margin(0));
+    let path = env::temp_dir().join("imported_draws_test.pkl");
+    let output = path.to_str().unwrap();
+
+    save_imported(&game, &[record], output, true, 0.5);
+    let history: VecDeque<Vec<Example<MnkGame>>> =
+        serde_pickle::from_slice(&fs::read(&path).unwrap(), Default::default()).unwrap();
+    // eight symmetries of each of the nine positions
+    assert_eq!(history[0].len(), 72);
+    assert!(history[0].iter().all(|(_, _, v)| *v == 0.5));
+    fs::remove_file(path).unwrap();
+}
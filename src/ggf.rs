@@ -0,0 +1,323 @@
+//! The Generic Game Format of Othello servers: a game is written as "(;"
+//! followed by properties KEY[value] and ";)". The properties used here are
+//! GM (the game), PB and PW (the players), TY (the board size), BO (the start
+//! position), B and W (the moves of each color, as "move/evaluation/time")
+//! and RE (the result for black, with a suffix for a game that was not played
+//! out). Evaluations are the values of MoveRecord.
+
+use std::{fmt, fs, path::Path};
+
+use crate::{
+    board::Board,
+    game::{Forfeit, Game, GameResult, Outcome},
+    game_record::{self, GameRecord, MoveRecord},
+    notation::{NotationError, PASS},
+    othello::{Othello, PositionError},
+};
+
+#[derive(Debug)]
+pub enum GgfError {
+    /// The text is not a GGF game.
+    Syntax(String),
+    /// The game is not Othello.
+    NotOthello(String),
+    Position(PositionError),
+    /// A move could not be replayed.
+    Notation(NotationError),
+    /// A move is written for the player who is not to move.
+    WrongColor {
+        ply: usize,
+    },
+    /// The replayed game does not end with the recorded result.
+    ResultMismatch {
+        recorded: GameResult,
+        replayed: Option<GameResult>,
+    },
+}
+
+impl fmt::Display for GgfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GgfError::Syntax(e) => write!(f, "invalid GGF: {e}"),
+            GgfError::NotOthello(game) => write!(f, "{game} is not Othello"),
+            GgfError::Position(e) => write!(f, "invalid board: {e}"),
+            GgfError::Notation(e) => write!(f, "{e}"),
+            GgfError::WrongColor { ply } => write!(f, "the move at ply {ply} has the wrong color"),
+            GgfError::ResultMismatch { recorded, replayed } => write!(
+                f,
+                "the game was recorded as {recorded:?} but replays to {replayed:?}"
+            ),
+        }
+    }
+}
+
+/// Splits the text between "(;" and ";)" into (key, value) properties.
+fn properties(text: &str) -> Result<Vec<(&str, &str)>, GgfError> {
+    let mut properties = Vec::new();
+    let mut rest = text.trim();
+    while !rest.is_empty() {
+        let open = rest
+            .find('[')
+            .ok_or_else(|| GgfError::Syntax(format!("\"{rest}\" is not a property")))?;
+        let key = rest[..open].trim();
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_uppercase()) {
+            return Err(GgfError::Syntax(format!(
+                "\"{key}\" is not a property name"
+            )));
+        }
+        let close = open
+            + rest[open..]
+                .find(']')
+                .ok_or_else(|| GgfError::Syntax(format!("{key} is not closed")))?;
+        properties.push((key, &rest[open + 1..close]));
+        rest = rest[close + 1..].trim_start();
+    }
+    Ok(properties)
+}
+
+/// Parses a BO value: the board size, the rows of the board and the side to
+/// move, with "*" for black, "O" for white and "-" for an empty square. The
+/// board is sized after its rows.
+fn parse_board(bo: &str) -> Result<(Othello, Board, i8), GgfError> {
+    let tokens: Vec<&str> = bo.split_whitespace().collect();
+    let invalid = || GgfError::Syntax(format!("\"{bo}\" is not a board"));
+    if tokens.len() < 4 {
+        return Err(invalid());
+    }
+    let rows = &tokens[1..tokens.len() - 1];
+    let cols = rows[0].len();
+    if rows.iter().any(|r| r.len() != cols) {
+        return Err(invalid());
+    }
+    Othello::check_size(rows.len(), cols).map_err(GgfError::Position)?;
+    let othello = Othello::new_rect(rows.len(), cols);
+    let (board, player) = othello
+        .parse_position(&tokens[1..].concat())
+        .map_err(GgfError::Position)?;
+    Ok((othello, board, player))
+}
+
+/// Parses a result such as "+18.000", "-4.000", "0.000" or "+64.000:r". A
+/// suffix (":r" for a resignation, ":t" for time and ":s" for an agreed
+/// score) marks a game that was not played out.
+fn parse_result(re: &str) -> Result<GameResult, GgfError> {
+    let (score, how) = re.trim().split_once(':').unwrap_or((re.trim(), ""));
+    let margin = score
+        .parse::<f32>()
+        .map_err(|_| GgfError::Syntax(format!("\"{re}\" is not a result")))?
+        .round() as i32;
+    let mut result = GameResult::from_margin(margin);
+    result.forfeit = match how {
+        "" => None,
+        "r" => Some(Forfeit::Resignation),
+        "t" => Some(Forfeit::Time),
+        "s" => Some(Forfeit::AgreedScore),
+        _ => return Err(GgfError::Syntax(format!("\"{re}\" is not a result"))),
+    };
+    if result.forfeit.is_some() {
+        if margin == 0 && score.starts_with('-') {
+            result.outcome = Outcome::Loss;
+        } else if margin == 0 && score.starts_with('+') {
+            result.outcome = Outcome::Win;
+        }
+    }
+    Ok(result)
+}
+
+/// Parses a time in seconds, "minutes:seconds" or "hours:minutes:seconds".
+fn parse_time(time: &str) -> Option<f32> {
+    time.trim()
+        .split(':')
+        .try_fold(0., |t, part| Some(t * 60. + part.parse::<f32>().ok()?))
+}
+
+/// Parses the text of a game between "(;" and ";)", replaying its moves
+/// through Othello on the board size of the game. A finished game must end
+/// with the recorded result, if there is one.
+pub fn parse_game(text: &str) -> Result<GameRecord<Board>, GgfError> {
+    let properties = properties(text)?;
+    let get = |key: &str| properties.iter().find(|(k, _)| *k == key).map(|(_, v)| *v);
+    if let Some(game) = get("GM").filter(|g| !g.eq_ignore_ascii_case("othello")) {
+        return Err(GgfError::NotOthello(game.to_owned()));
+    }
+    let bo = get("BO").ok_or_else(|| GgfError::Syntax("the game has no BO".to_owned()))?;
+    let (othello, mut board, mut player) = parse_board(bo)?;
+    let mut record = GameRecord::new(board, player);
+    for (i, key) in ["PB", "PW"].iter().enumerate() {
+        if let Some(name) = get(key) {
+            record.players[i] = name.to_owned();
+        }
+    }
+
+    for (key, value) in properties.iter().filter(|(k, _)| *k == "B" || *k == "W") {
+        let ply = record.moves.len() + 1;
+        if player != if *key == "B" { 1 } else { -1 } {
+            return Err(GgfError::WrongColor { ply });
+        }
+        let mut fields = value.split('/');
+        // split returns at least one field
+        let text = fields.next().unwrap().trim();
+        let action = othello
+            .string_to_action(text)
+            .ok_or_else(|| GgfError::Notation(NotationError::InvalidMove(text.to_owned())))?;
+        (board, player) = othello
+            .try_next_state(&board, player, action)
+            .map_err(|_| {
+                GgfError::Notation(NotationError::IllegalMove {
+                    ply,
+                    action: othello.action_to_string(action),
+                })
+            })?;
+        let mut m = MoveRecord::new(othello.action_to_string(action))
+            .with_value(fields.next().and_then(|e| e.trim().parse::<f32>().ok()));
+        if let Some(time) = fields.next().and_then(parse_time) {
+            m = m.with_time_ms(time * 1000.);
+        }
+        record.moves.push(m);
+    }
+
+    let mut replayed = othello.get_game_ended(&board, player);
+    if player == -1 {
+        replayed = replayed.map(|r| r.for_opponent());
+    }
+    record.result = match get("RE").map(parse_result).transpose()? {
+        Some(recorded) if recorded.forfeit.is_none() && Some(recorded) != replayed => {
+            return Err(GgfError::ResultMismatch { recorded, replayed });
+        }
+        Some(recorded) => Some(recorded),
+        None => replayed,
+    };
+    Ok(record)
+}
+
+/// Parses every game of a GGF archive, in which games follow each other,
+/// usually one per line.
+///
+/// Returns:
+///     records: the records of the valid games
+///     rejected: the index and error of every invalid game
+pub fn parse_archive(text: &str) -> (Vec<GameRecord<Board>>, Vec<(usize, GgfError)>) {
+    let mut records = Vec::new();
+    let mut rejected = Vec::new();
+    let mut rest = text;
+    let mut i = 0;
+    while let Some(start) = rest.find("(;") {
+        let Some(len) = rest[start..].find(";)") else {
+            rejected.push((i, GgfError::Syntax("the game is not closed".to_owned())));
+            break;
+        };
+        match parse_game(&rest[start + 2..start + len]) {
+            Ok(record) => records.push(record),
+            Err(e) => rejected.push((i, e)),
+        }
+        rest = &rest[start + len + 2..];
+        i += 1;
+    }
+    (records, rejected)
+}
+
+/// Writes record, an Othello game, as GGF on one line.
+pub fn to_ggf(record: &GameRecord<Board>) -> String {
+    let board = &record.start_board;
+    let (rows, cols) = board.size();
+    let size = if rows == cols {
+        rows.to_string()
+    } else {
+        format!("{rows}x{cols}")
+    };
+    let board_rows: Vec<String> = (0..rows)
+        .map(|x| {
+            (0..cols)
+                .map(|y| match board.get(x, y) {
+                    1 => '*',
+                    -1 => 'O',
+                    _ => '-',
+                })
+                .collect()
+        })
+        .collect();
+    let side = if record.start_player == 1 { '*' } else { 'O' };
+
+    let [black, white] = &record.players;
+    let mut ggf = format!(
+        "(;GM[Othello]PB[{black}]PW[{white}]TY[{size}]BO[{size} {} {side}]",
+        board_rows.join(" ")
+    );
+    let mut color = record.start_player;
+    for m in &record.moves {
+        let action = if m.action == PASS {
+            "PA".to_owned()
+        } else {
+            m.action.to_ascii_uppercase()
+        };
+        let key = if color == 1 { "B" } else { "W" };
+        ggf.push_str(&format!("{key}[{action}"));
+        if m.value.is_some() || m.time_ms.is_some() {
+            let eval = m.value.map_or(String::new(), |v| format!("{v:.2}"));
+            let time = m
+                .time_ms
+                .map_or(String::new(), |t| format!("{:.2}", t / 1000.));
+            ggf.push_str(&format!("/{eval}/{time}"));
+        }
+        ggf.push(']');
+        // passes are moves in Othello, so the colors alternate
+        color = -color;
+    }
+    if let Some(result) = record.result {
+        let sign = match result.outcome {
+            Outcome::Win => "+",
+            Outcome::Loss => "-",
+            Outcome::Draw => "",
+        };
+        let how = match result.forfeit {
+            None => "",
+            // GGF has no illegal actions, the closest is a resignation
+            Some(Forfeit::IllegalAction | Forfeit::Resignation) => ":r",
+            Some(Forfeit::Time) => ":t",
+            Some(Forfeit::AgreedScore) => ":s",
+        };
+        ggf.push_str(&format!("RE[{sign}{}.000{how}]", result.margin.abs()));
+    }
+    ggf.push_str(";)");
+    ggf
+}
+
+/// Runs `othello ggf <file> [output file] [examples]`, importing the Othello
+/// games of a GGF archive. They are saved to the output file as game records,
+/// or as the training examples of the games of the size of the first one,
+/// with draws worth draw_value, see game_record::save_imported.
+pub fn command(args: &[String], draw_value: f32) {
+    let path = Path::new(
+        args.first()
+            .expect("Usage: othello ggf <file> [output file] [examples]"),
+    );
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => return println!("Could not read {}: {e}", path.display()),
+    };
+    let (mut records, rejected) = parse_archive(&text);
+    for (i, e) in &rejected {
+        println!("Rejected game {i}: {e}");
+    }
+    println!("{} games, {} rejected", records.len(), rejected.len());
+    let (Some(output), Some(first)) = (args.get(1), records.first()) else {
+        return;
+    };
+    let (rows, cols) = first.start_board.size();
+    let examples = args.get(2).is_some_and(|v| v == "examples");
+    if examples {
+        let games = records.len();
+        records.retain(|r| r.start_board.size() == (rows, cols));
+        if records.len() < games {
+            println!(
+                "Skipping {} games of another size than {rows}x{cols}",
+                games - records.len()
+            );
+        }
+    }
+    let othello = Othello::new_rect(rows, cols);
+    game_record::save_imported(&othello, &records, output, examples, draw_value);
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,192 @@
+use rand::Rng;
+
+use super::{parse_archive, parse_game, parse_result, parse_time, to_ggf, GgfError};
+use crate::{
+    arena::Arena,
+    board::Board,
+    game::{Forfeit, Game, GameResult, Outcome},
+    game_record::GameRecord,
+    notation::NotationError,
+    othello::Othello,
+};
+
+const GAME: &str = "GM[Othello]PC[NIOS]DT[2003.12.15_13:24:03.MST]PB[alice]PW[bob]TY[8]\
+    TI[15:00//02:00]BO[8 -------- -------- -------- ---O*--- ---*O--- -------- -------- -------- *]\
+    B[f5//1.25]W[D6/-1.50/0:02]B[c3]";
+
+fn random_records(othello: &Othello, games: usize) -> Vec<GameRecord<Board>> {
+    let player = |b: &Board| {
+        let valids = othello.get_valid_moves(b, 1);
+        let legal: Vec<usize> = (0..valids.len()).filter(|a| valids[*a] == 1).collect();
+        othello.index_to_action(legal[rand::thread_rng().gen_range(0..legal.len())])
+    };
+    let mut arena = Arena::new(player, player, othello, |_: &Board| {}).with_names("one", "two");
+    arena.play_games(games, false);
+    arena.take_records()
+}
+
+#[test]
+fn parse_game_with_evals_and_times() {
+    let record = parse_game(GAME).unwrap();
+    let othello = Othello::new(8);
+    assert_eq!(&record.start_board, othello.get_init_board());
+    assert_eq!(record.start_player, 1);
+    assert_eq!(record.players, ["alice".to_owned(), "bob".to_owned()]);
+    let actions: Vec<&str> = record.moves.iter().map(|m| m.action.as_str()).collect();
+    assert_eq!(actions, vec!["f5", "d6", "c3"]);
+    assert_eq!(record.moves[0].value, None);
+    assert_eq!(record.moves[0].time_ms, Some(1250.));
+    assert_eq!(record.moves[1].value, Some(-1.5));
+    assert_eq!(record.moves[1].time_ms, Some(2000.));
+    assert_eq!(record.moves[2].time_ms, None);
+    assert_eq!(record.result, None);
+}
+
+#[test]
+fn arena_games_round_trip() {
+    for othello in [
+        Othello::new(6),
+        Othello::new_rect(4, 6),
+        Othello::new_rect(4, 16),
+        Othello::new_rect(16, 4),
+    ] {
+        for record in random_records(&othello, 4) {
+            let ggf = to_ggf(&record);
+            assert!(ggf.starts_with("(;GM[Othello]PB[one]PW[two]"));
+            assert!(ggf.ends_with(";)"));
+            let parsed = parse_game(&ggf[2..ggf.len() - 2]).unwrap();
+            assert_eq!(parsed.start_board, record.start_board);
+            assert_eq!(parsed.start_player, record.start_player);
+            assert_eq!(parsed.players, record.players);
+            assert_eq!(parsed.result, record.result);
+            for (p, m) in parsed.moves.iter().zip(&record.moves) {
+                assert_eq!(p.action, m.action);
+                assert!((p.time_ms.unwrap() - m.time_ms.unwrap()).abs() <= 5.);
+            }
+            assert_eq!(parsed.moves.len(), record.moves.len());
+        }
+    }
+}
+
+#[test]
+fn results_for_black() {
+    assert_eq!(
+        parse_result("+18.000").unwrap(),
+        GameResult::from_margin(18)
+    );
+    assert_eq!(parse_result("-4.000").unwrap(), GameResult::from_margin(-4));
+    assert_eq!(parse_result("0.000").unwrap(), GameResult::from_margin(0));
+    let resigned = parse_result("+64.000:r").unwrap();
+    assert_eq!(resigned.outcome, Outcome::Win);
+    assert_eq!(resigned.forfeit, Some(Forfeit::Resignation));
+    let timed_out = parse_result("-0.000:t").unwrap();
+    assert_eq!(timed_out.outcome, Outcome::Loss);
+    assert_eq!(timed_out.forfeit, Some(Forfeit::Time));
+    let agreed = parse_result("+0.000:s").unwrap();
+    assert_eq!(agreed.outcome, Outcome::Win);
+    assert_eq!(agreed.forfeit, Some(Forfeit::AgreedScore));
+    assert!(matches!(parse_result("?"), Err(GgfError::Syntax(_))));
+    assert!(matches!(parse_result("+2.000:x"), Err(GgfError::Syntax(_))));
+
+    assert_eq!(parse_time("1.5"), Some(1.5));
+    assert_eq!(parse_time("2:30"), Some(150.));
+    assert_eq!(parse_time(""), None);
+}
+
+#[test]
+fn forfeits_are_written_as_resignations() {
+    let othello = Othello::new(6);
+    let mut record = random_records(&othello, 2).pop().unwrap();
+    record.moves.truncate(3);
+    record.result = Some(GameResult::forfeit().for_opponent());
+    let ggf = to_ggf(&record);
+    assert!(ggf.ends_with("RE[+0.000:r];)"));
+    let parsed = parse_game(&ggf[2..ggf.len() - 2]).unwrap();
+    assert_eq!(parsed.result.unwrap().outcome, Outcome::Win);
+    assert_eq!(parsed.result.unwrap().forfeit, Some(Forfeit::Resignation));
+}
+
+#[test]
+fn imported_forfeits_keep_their_suffix() {
+    let othello = Othello::new(6);
+    let mut record = random_records(&othello, 2).pop().unwrap();
+    record.moves.truncate(3);
+    for (forfeit, suffix) in [
+        (Forfeit::Resignation, ":r"),
+        (Forfeit::Time, ":t"),
+        (Forfeit::AgreedScore, ":s"),
+    ] {
+        let mut result = GameResult::from_margin(-6);
+        result.forfeit = Some(forfeit);
+        record.result = Some(result);
+        let ggf = to_ggf(&record);
+        assert!(ggf.ends_with(&format!("RE[-6.000{suffix}];)")));
+        let parsed = parse_game(&ggf[2..ggf.len() - 2]).unwrap();
+        assert_eq!(parsed.result, record.result);
+    }
+}
+
+#[test]
+fn invalid_games_are_rejected() {
+    assert!(matches!(
+        parse_game(&GAME.replace("GM[Othello]", "GM[Go]")),
+        Err(GgfError::NotOthello(_))
+    ));
+    assert!(matches!(
+        parse_game(&GAME.replace("W[D6", "B[D6")),
+        Err(GgfError::WrongColor { ply: 2 })
+    ));
+    assert!(matches!(
+        parse_game(&GAME.replace("B[c3]", "B[a1]")),
+        Err(GgfError::Notation(NotationError::IllegalMove {
+            ply: 3,
+            ..
+        }))
+    ));
+    assert!(matches!(
+        parse_game(&GAME.replace("B[c3]", "B[k3]")),
+        Err(GgfError::Notation(NotationError::InvalidMove(_)))
+    ));
+    assert!(matches!(
+        parse_game(&GAME.replace("---O*---", "---O#---")),
+        Err(GgfError::Position(_))
+    ));
+    assert!(matches!(
+        parse_game(&GAME.replace("---O*---", "---O*--")),
+        Err(GgfError::Syntax(_))
+    ));
+    assert!(matches!(
+        parse_game("GM[Othello]PB[x"),
+        Err(GgfError::Syntax(_))
+    ));
+    // the game is not over
+    assert!(matches!(
+        parse_game(&format!("{GAME}RE[+10.000]")),
+        Err(GgfError::ResultMismatch { .. })
+    ));
+    let resigned = parse_game(&format!("{GAME}RE[-10.000:r]")).unwrap();
+    assert_eq!(resigned.result.unwrap().outcome, Outcome::Loss);
+}
+
+#[test]
+fn parse_archive_of_games() {
+    let othello = Othello::new(6);
+    let records = random_records(&othello, 2);
+    let mut archive = String::new();
+    for record in &records {
+        archive.push_str(&to_ggf(record));
+        archive.push('\n');
+    }
+    archive.push_str(&format!("(;{};)\n", GAME.replace("B[c3]", "B[a1]")));
+    archive.push_str(&format!("(;{GAME};)\n"));
+    archive.push_str("(;GM[Othello]");
+
+    let (parsed, rejected) = parse_archive(&archive);
+    assert_eq!(parsed.len(), 3);
+    assert_eq!(parsed[1].moves.len(), records[1].moves.len());
+    assert_eq!(parsed[2].start_board.size(), (8, 8));
+    assert_eq!(
+        rejected.iter().map(|(i, _)| *i).collect::<Vec<usize>>(),
+        vec![2, 4]
+    );
+}
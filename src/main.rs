@@ -1,22 +1,37 @@
 use std::collections::HashMap;
-use std::io;
+use std::{env, io};
 
 use burn::backend::{libtorch::LibTorchDevice, Autodiff, LibTorch};
 
 use crate::{
-    coach::Coach, game::Game, n_net::NNetWrapper, neural_net::NeuralNet, othello::Othello,
+    coach::Coach,
+    connect_four::ConnectFour,
+    game::Game,
+    mnk_game::MnkGame,
+    n_net::NNetWrapper,
+    neural_net::NeuralNet,
+    othello::{Othello, Rules},
 };
 
 mod arena;
 mod board;
 mod board_math;
 mod coach;
+mod connect_four;
 mod game;
+mod game_record;
+mod ggf;
 mod mcts;
+mod mnk_game;
 mod n_net;
 mod neural_net;
+mod notation;
 mod othello;
 mod othello_neural_net;
+mod perft;
+mod tensor;
+mod wthor;
+mod zobrist;
 
 fn main() {
     let mut args: HashMap<String, String> = HashMap::new();
@@ -28,6 +43,7 @@ fn main() {
     args.insert("numMCTSSims".to_string(), "25".to_owned());
     args.insert("arenaCompare".to_string(), "40".to_owned());
     args.insert("cpuct".to_string(), "1".to_owned());
+    args.insert("drawValue".to_string(), "0".to_owned());
     args.insert("checkpoint".to_string(), "./temp/".to_owned());
     args.insert("load_model".to_string(), "true".to_owned());
     args.insert("load_folder".to_string(), "temp".to_owned());
@@ -38,14 +54,89 @@ fn main() {
         "20".to_owned(),
     );
     args.insert("verbose".to_owned(), "false".to_owned());
+    args.insert("checkHashCollisions".to_owned(), "false".to_owned());
+    // keep the records of self-play and arena games in the checkpoint folder
+    args.insert("saveGames".to_owned(), "true".to_owned());
+    // records of games to add to the training examples, e.g. "temp/arena_1.bin"
+    args.insert("trainGames".to_owned(), "".to_owned());
+    // Dirichlet noise mixed into the root priors of self-play searches, none
+    // with an epsilon of 0
+    args.insert("dirichletAlpha".to_owned(), "0.3".to_owned());
+    args.insert("dirichletEpsilon".to_owned(), "0.25".to_owned());
+    // reroot the search tree at every move instead of starting a new one
+    args.insert("selfPlayReuseTree".to_owned(), "true".to_owned());
+    args.insert("arenaReuseTree".to_owned(), "true".to_owned());
+    // evaluate the positions of the search on their canonical symmetry
+    args.insert("canonicalSymmetry".to_owned(), "false".to_owned());
+    // merge the training examples of positions that are symmetries of each other
+    args.insert("deduplicateExamples".to_owned(), "false".to_owned());
+    // leaves the search evaluates in one batch, and the loss a pending visit counts for
+    args.insert("leafBatchSize".to_owned(), "1".to_owned());
+    args.insert("virtualLoss".to_owned(), "1".to_owned());
+    // threads searching one tree, which batch their leaves in place of leafBatchSize
+    args.insert("searchThreads".to_owned(), "1".to_owned());
+    // limits of every search besides numMCTSSims, none with 0
+    args.insert("searchTimeMs".to_owned(), "0".to_owned());
+    args.insert("searchNodes".to_owned(), "0".to_owned());
+    args.insert("searchMemoryMb".to_owned(), "0".to_owned());
+    // stop arena searches once their best move can no longer change
+    args.insert("earlyStop".to_owned(), "true".to_owned());
+    // othello, connect4, gomoku, tictactoe or mnk:<m>:<n>:<k>
+    args.insert("game".to_owned(), "othello".to_owned());
+    // standard or anti
+    args.insert("rules".to_owned(), "standard".to_owned());
+    // standard, random:<count>:<plies>, handicap:<corners> or position:<position>
+    args.insert("openings".to_owned(), "standard".to_owned());
 
-    println!("Loading {:?}...", "Othello");
-    let g = Othello::new(6);
+    let cli: Vec<String> = env::args().collect();
+    // imported games are worth drawValue as self-play games
+    let draw_value = args.get("drawValue").unwrap().parse::<f32>().unwrap();
+    match cli.get(1).map(|c| c.as_str()) {
+        Some("perft") => return perft::command(&cli[2..]),
+        Some("replay") => return notation::command(&cli[2..]),
+        Some("games") => return game_record::command(&cli[2..]),
+        Some("ggf") => return ggf::command(&cli[2..], draw_value),
+        Some("wthor") => return wthor::command(&cli[2..], draw_value),
+        Some("bench") => {
+            return mcts::bench_command::<Autodiff<LibTorch>>(&cli[2..], LibTorchDevice::Cpu)
+        }
+        _ => {}
+    }
 
+    let game = args.get("game").unwrap().clone();
+    match game.as_str() {
+        "othello" => {
+            let rules = args.get("rules").unwrap().parse::<Rules>().unwrap();
+            println!("Loading {:?} with {:?} rules...", "Othello", rules);
+            let g = Othello::new(6).with_rules(rules);
+            let openings = match g.openings_from_arg(args.get("openings").unwrap()) {
+                Ok(openings) => openings,
+                Err(e) => return println!("Invalid openings: {e}"),
+            };
+            if !openings.is_empty() {
+                println!("Starting games from {} openings", openings.len());
+            }
+            learn(g.with_openings(openings), args);
+        }
+        "connect4" => {
+            println!("Loading {:?}...", "ConnectFour");
+            learn(ConnectFour::new(6), args);
+        }
+        game => match MnkGame::from_arg(game) {
+            Some(g) => {
+                println!("Loading {:?}...", game);
+                learn(g, args);
+            }
+            None => println!("Unknown game {game:?}"),
+        },
+    }
+}
+
+fn learn<G: Game + Clone>(g: G, args: HashMap<String, String>) {
     println!("Loading {:?}...", "LibTorch");
     let device = LibTorchDevice::Cuda(0);
     type MyBackend = Autodiff<LibTorch>;
-    let mut nnet: NNetWrapper<MyBackend, Othello> = NNetWrapper::new(g.clone(), device);
+    let mut nnet: NNetWrapper<MyBackend, G> = NNetWrapper::new(g.clone(), device);
 
     let load_model = args.get("load_model").unwrap().parse::<bool>().unwrap();
     if load_model {
@@ -58,13 +149,18 @@ fn main() {
     }
 
     println!("Loading the Coach...");
-    let mut c = Coach::new(g.clone(), nnet, device, args);
+    let train_games = !args.get("trainGames").unwrap().is_empty();
+    let mut c = Coach::new(g.clone(), nnet, args);
 
     // TODO potential loading in of training examples
     if load_model {
         println!("Loading 'trainExamples' from file...");
         c.load_train_examples();
     }
+    if train_games {
+        println!("Loading training examples from games...");
+        c.load_train_games();
+    }
 
     println!("Starting the learning process 🎉");
     c.learn();
@@ -1,56 +1,261 @@
-use std::{collections::HashMap, marker::PhantomData};
+use std::{collections::HashMap, marker::PhantomData, time::Instant};
 
 use burn::tensor::backend::AutodiffBackend;
-use rand::seq::SliceRandom;
+use rand::{seq::SliceRandom, Rng};
+use rand_distr::{Distribution, Gamma};
 
 use crate::{game::Game, n_net::NNetWrapper, neural_net::NeuralNet};
+use tree::{EdgeId, NodeId, Tree, ROOT};
 
-pub struct MCTS<G: Game, B: AutodiffBackend> {
+pub use budget::{SearchBudget, SearchReport, StopReason};
+pub use tree::TreeMemory;
+
+pub struct MCTS<G: Game, B: AutodiffBackend, N: NeuralNet<B, G> = NNetWrapper<B, G>> {
     game: G,
-    nnet: NNetWrapper<B, G>,
-    args: HashMap<String, String>,
+    nnet: N,
     phantom: PhantomData<B>,
-    qsa: HashMap<(String, usize), f32>,
-    nsa: HashMap<(String, usize), f32>,
-    ns: HashMap<String, usize>,
-    ps: HashMap<String, Vec<f32>>,
-    es: HashMap<String, i8>,
-    vs: HashMap<String, Vec<u8>>,
+    // the tree below the board of the last search
+    tree: Tree,
+    cpuct: f32,
+    draw_value: f32,
+    // evaluate leaves on their canonical symmetry
+    canonical_symmetry: bool,
+    // (alpha, epsilon) of the noise mixed into the priors of the root
+    root_noise: Option<(f32, f32)>,
+    // keep the statistics below the root from one search to the next
+    reuse_tree: bool,
+    // number of leaves evaluated together after the first simulation
+    leaf_batch_size: usize,
+    // value of a pending visit of a batched search, counted as a loss
+    virtual_loss: f32,
+    // threads searching one shared tree after the first simulation
+    threads: usize,
+    // limits of the searches of get_action_prob
+    budget: SearchBudget,
+    last_search: Option<SearchReport>,
+}
+
+/// The settings of MCTS that a descent reads.
+#[derive(Clone, Copy)]
+struct Settings {
+    cpuct: f32,
+    virtual_loss: f32,
+    draw_value: f32,
+}
+
+/// Returns (1 - epsilon) * priors + epsilon * noise, where noise follows a
+/// symmetric Dirichlet distribution of parameter alpha over the valid moves.
+pub fn mix_dirichlet_noise(
+    priors: &[f32],
+    valids: &[u8],
+    alpha: f32,
+    epsilon: f32,
+    rng: &mut impl Rng,
+) -> Vec<f32> {
+    let gamma = Gamma::new(alpha, 1.).expect("alpha should be positive");
+    let mut noise: Vec<f32> = valids
+        .iter()
+        .map(|v| if *v == 1 { gamma.sample(rng) } else { 0. })
+        .collect();
+    let mut sum: f32 = noise.iter().sum();
+    if sum == 0. {
+        // every sample underflowed, which happens for a tiny alpha
+        noise = valids.iter().map(|v| *v as f32).collect();
+        sum = noise.iter().sum();
+    }
+    priors
+        .iter()
+        .zip(noise)
+        .map(|(p, n)| (1. - epsilon) * p + epsilon * n / sum)
+        .collect()
 }
 
-impl<G: Game, B: AutodiffBackend> MCTS<G, B> {
-    pub fn new(game: G, nnet: NNetWrapper<B, G>, args: HashMap<String, String>) -> Self {
+impl<G: Game, B: AutodiffBackend, N: NeuralNet<B, G>> MCTS<G, B, N> {
+    pub fn new(game: G, nnet: N, args: HashMap<String, String>) -> Self {
+        let check_collisions = args
+            .get("checkHashCollisions")
+            .is_some_and(|v| v.parse::<bool>().unwrap());
+        let draw_value = args
+            .get("drawValue")
+            .map_or(0., |v| v.parse::<f32>().unwrap());
+        let canonical_symmetry = args
+            .get("canonicalSymmetry")
+            .is_some_and(|v| v.parse::<bool>().unwrap());
+        let leaf_batch_size = args
+            .get("leafBatchSize")
+            .map_or(1, |v| v.parse::<usize>().unwrap());
+        let virtual_loss = args
+            .get("virtualLoss")
+            .map_or(1., |v| v.parse::<f32>().unwrap());
+        let threads = args
+            .get("searchThreads")
+            .map_or(1, |v| v.parse::<usize>().unwrap());
+        let cpuct = args.get("cpuct").unwrap().parse::<f32>().unwrap();
         MCTS {
             game,
             nnet,
-            args,
             phantom: PhantomData,
-            qsa: HashMap::new(),
-            nsa: HashMap::new(),
-            ns: HashMap::new(),
-            ps: HashMap::new(),
-            es: HashMap::new(),
-            vs: HashMap::new(),
+            tree: Tree::new(check_collisions),
+            cpuct,
+            draw_value,
+            canonical_symmetry,
+            root_noise: None,
+            reuse_tree: true,
+            leaf_batch_size,
+            virtual_loss,
+            threads,
+            budget: SearchBudget::from_args(&args),
+            last_search: None,
+        }
+    }
+
+    /// Mixes Dirichlet noise of parameter alpha into the priors of the root
+    /// of every search, with weight epsilon, so that self-play explores moves
+    /// the network does not favor. Evaluation games should search without it.
+    pub fn with_root_noise(mut self, alpha: f32, epsilon: f32) -> Self {
+        self.root_noise = Some((alpha, epsilon));
+        self
+    }
+
+    /// With reuse, every search is rerooted at its board, keeping the visits
+    /// of earlier searches below it and freeing the rest of the tree. Without,
+    /// every search starts from an empty tree. Searches reuse the tree by
+    /// default.
+    pub fn with_tree_reuse(mut self, reuse: bool) -> Self {
+        self.reuse_tree = reuse;
+        self
+    }
+
+    /// Limits the searches of get_action_prob to budget, in place of the one
+    /// that the args set, see SearchBudget::from_args.
+    pub fn with_budget(mut self, budget: SearchBudget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Returns what the last search did, None before the first one.
+    pub fn last_search(&self) -> Option<SearchReport> {
+        self.last_search
+    }
+
+    /// Keeps the subtree of canonicalBoard that the searches so far reached,
+    /// and frees the rest of the tree.
+    pub fn reroot(&mut self, canonical_board: &G::State) {
+        if !self.tree.reroot(self.game.zobrist_hash(canonical_board)) {
+            self.tree.clear();
         }
     }
 
-    /// This function performs numMCTSSims simulations of MCTS starting from
-    /// canonicalBoard.
+    /// Frees the whole tree.
+    pub fn clear(&mut self) {
+        self.tree.clear();
+    }
+
+    /// Returns the size of the tree.
+    pub fn memory(&self) -> TreeMemory {
+        self.tree.memory()
+    }
+
+    /// This function performs the simulations of MCTS that the budget of the
+    /// search allows, numMCTSSims by default, starting from canonicalBoard.
     ///
     /// Returns:
     ///     probs: a policy vector where the probability of the ith action is
     ///            proportional to Nsa[(s,a)]**(1./temp)
-    pub fn get_action_prob(&mut self, canonical_board: &Vec<Vec<i8>>, temp: u8) -> Vec<f32> {
-        for _i in 0..self.args.get("numMCTSSims").unwrap().parse().unwrap() {
-            self.search(canonical_board);
+    pub fn get_action_prob(&mut self, canonical_board: &G::State, temp: u8) -> Vec<f32> {
+        let budget = self.budget.clone();
+        self.get_action_prob_within(canonical_board, temp, &budget)
+            .0
+    }
+
+    /// Like get_action_prob, with the simulations that budget allows, e.g. a
+    /// deadline for a move of a timed game or a stop signal from the user.
+    ///
+    /// Returns:
+    ///     probs: as get_action_prob
+    ///     report: what the search did, also kept for last_search
+    pub fn get_action_prob_within(
+        &mut self,
+        canonical_board: &G::State,
+        temp: u8,
+        budget: &SearchBudget,
+    ) -> (Vec<f32>, SearchReport) {
+        let report = self.search_within(canonical_board, budget);
+        self.last_search = Some(report);
+        (self.action_prob(temp), report)
+    }
+
+    /// Performs simulations from canonicalBoard until budget runs out, at
+    /// least one so that the root is expanded. Panics if the budget has no
+    /// limit, see SearchBudget::is_limited. With searchThreads above 1,
+    /// the simulations after the first are run by that many threads, see
+    /// search_parallel. Otherwise, with leafBatchSize above 1, they are run in
+    /// batches of that many leaves, see search_batch.
+    fn search_within(&mut self, canonical_board: &G::State, budget: &SearchBudget) -> SearchReport {
+        // the args are checked by Coach::new
+        assert!(budget.is_limited(), "the search budget should have a limit");
+        let start = Instant::now();
+        if self.reuse_tree {
+            self.reroot(canonical_board);
+        } else {
+            self.clear();
+        }
+        if self.tree.is_empty() {
+            let s = self.game.zobrist_hash(canonical_board);
+            let result = self.game.get_game_ended(canonical_board, 1);
+            self.tree.add_node(s, result, || {
+                self.game.string_representation(canonical_board)
+            });
+        }
+        // a root kept from an earlier search is already expanded, and gets its
+        // noise before the first simulation descends from it
+        let fresh_root = !self.tree.is_expanded(ROOT);
+        let mut priors = self.add_root_noise();
+        let mut i = 0;
+        let reason = loop {
+            if i > 0 {
+                if budget.simulations.is_some_and(|sims| i >= sims) {
+                    break StopReason::Simulations;
+                }
+                if let Some(reason) = budget.check(&self.tree, i, start) {
+                    break reason;
+                }
+            }
+            if i == 0 || (self.leaf_batch_size == 1 && self.threads == 1) {
+                self.search(canonical_board);
+                i += 1;
+            } else if self.threads > 1 {
+                let (done, reason) = self.search_parallel(canonical_board, budget, start, i);
+                i = done;
+                break reason;
+            } else {
+                let left = budget.simulations.map_or(usize::MAX, |sims| sims - i);
+                let k = self.leaf_batch_size.min(left);
+                self.search_batch(canonical_board, k);
+                i += k;
+            }
+            if i == 1 && fresh_root {
+                // the first simulation has expanded the root
+                priors = self.add_root_noise();
+            }
+        };
+        if let Some(priors) = priors {
+            // the noise is drawn anew for every search
+            self.tree.set_priors(ROOT, &priors);
         }
+        SearchReport {
+            simulations: i,
+            elapsed: start.elapsed(),
+            reason,
+        }
+    }
 
-        let s = self.game.string_representation(canonical_board);
-        let mut counts = Vec::<f32>::with_capacity(self.game.get_action_size());
-        for i in 0..self.game.get_action_size() {
-            let nsa_key = (s.clone(), i);
-            let nsa_value = self.nsa.get(&nsa_key).unwrap_or(&0.);
-            counts.push(*nsa_value);
+    /// Returns a policy vector where the probability of the ith action is
+    /// proportional to its visits at the root to the power 1/temp.
+    fn action_prob(&self, temp: u8) -> Vec<f32> {
+        let mut counts = vec![0.; self.game.get_action_size()];
+        for edge in self.tree.edges(ROOT) {
+            counts[edge.action as usize] = edge.visits() as f32;
         }
 
         if temp == 0 {
@@ -77,124 +282,265 @@ impl<G: Game, B: AutodiffBackend> MCTS<G, B> {
         return counts;
     }
 
-    /// This function performs one iteration of MCTS. It is recursively called
-    /// till a leaf node is found. The action chosen at each node is one that
-    /// has the maximum upper confidence bound as in the paper.
+    /// Mixes root noise into the priors of the root, if the search has noise
+    /// and the root is not terminal.
+    ///
+    /// Returns:
+    ///     priors: the priors of the root without noise
+    fn add_root_noise(&mut self) -> Option<Vec<f32>> {
+        let (alpha, epsilon) = self.root_noise?;
+        if !self.tree.is_expanded(ROOT) {
+            return None;
+        }
+        // the edges are the valid moves
+        let priors: Vec<f32> = self.tree.edges(ROOT).iter().map(|e| e.prior).collect();
+        let noisy = mix_dirichlet_noise(
+            &priors,
+            &vec![1; priors.len()],
+            alpha,
+            epsilon,
+            &mut rand::thread_rng(),
+        );
+        self.tree.set_priors(ROOT, &noisy);
+        Some(priors)
+    }
+
+    /// Returns:
+    ///     counts: the visit count of every action of canonicalBoard
+    ///     v: the average value of the simulations through canonicalBoard for
+    ///        the player to move, None when it was not searched
+    pub fn root_stats(&self, canonical_board: &G::State) -> (Vec<u32>, Option<f32>) {
+        let mut counts = vec![0; self.game.get_action_size()];
+        if self.tree.is_empty() || self.tree.node(ROOT).s != self.game.zobrist_hash(canonical_board)
+        {
+            return (counts, None);
+        }
+        let mut total = 0.;
+        let mut visits = 0;
+        for edge in self.tree.edges(ROOT) {
+            total += edge.value_sum();
+            visits += edge.visits();
+            counts[edge.action as usize] = edge.visits();
+        }
+        let v = if visits > 0 {
+            Some(total / visits as f32)
+        } else {
+            None
+        };
+        (counts, v)
+    }
+
+    fn settings(&self) -> Settings {
+        Settings {
+            cpuct: self.cpuct,
+            virtual_loss: self.virtual_loss,
+            draw_value: self.draw_value,
+        }
+    }
+
+    /// This function performs one iteration of MCTS. It descends from the
+    /// root till a leaf node is found. The action chosen at each node is one
+    /// that has the maximum upper confidence bound as in the paper.
     ///
     /// Once a leaf node is found, the neural network is called to return an
     /// initial policy P and a value v for the state. This value is propagated
     /// up the search path. In case the leaf node is a terminal state, the
-    /// outcome is propagated up the search path. The values of Ns, Nsa, Qsa are
-    /// updated.
+    /// outcome is propagated up the search path. The visits and values of the
+    /// nodes and edges of the path are updated.
     ///
-    /// NOTE: the return values are the negative of the value of the current
-    /// state. This is done since v is in [-1,1] and if v is the value of a
-    /// state for the current player, then its value is -v for the other player.
+    /// NOTE: the value of a node is -v for the player of its parent, since v
+    /// is in [-1,1] and the value of a state for the current player is -v for
+    /// the other player.
+    fn search(&mut self, canonical_board: &G::State) {
+        let (path, leaf, board) = self.descend(canonical_board, false);
+        let v = match self.tree.node(leaf).result {
+            // terminal node, draws are worth drawValue
+            Some(result) => -result.value(self.draw_value),
+            None => {
+                let (pi, v) = self.evaluate(&[&board]).pop().unwrap();
+                self.expand(leaf, &board, pi);
+                -v
+            }
+        };
+        self.tree.backup(&path, v, false);
+    }
+
+    /// Performs k simulations from canonicalBoard together. Each descent adds
+    /// a virtual loss to the edges it takes, so that the following descents
+    /// spread over other leaves; the leaves are then evaluated in one batch
+    /// and every path is backed up, removing its virtual loss. Descents that
+    /// end on the same leaf all back up its value.
+    fn search_batch(&mut self, canonical_board: &G::State, k: usize) {
+        let descents: Vec<_> = (0..k)
+            .map(|_| self.descend(canonical_board, true))
+            .collect();
+
+        let mut leaves: Vec<(NodeId, &G::State)> = Vec::new();
+        for (_, leaf, board) in &descents {
+            if self.tree.node(*leaf).result.is_none() && !leaves.iter().any(|(l, _)| l == leaf) {
+                leaves.push((*leaf, board));
+            }
+        }
+        let boards: Vec<&G::State> = leaves.iter().map(|(_, board)| *board).collect();
+        let predictions = self.evaluate(&boards);
+        let mut values = HashMap::new();
+        for ((leaf, board), (pi, v)) in leaves.iter().zip(predictions) {
+            self.expand(*leaf, board, pi);
+            values.insert(*leaf, -v);
+        }
+
+        for (path, leaf, _) in &descents {
+            let v = match self.tree.node(*leaf).result {
+                Some(result) => -result.value(self.draw_value),
+                None => values[leaf],
+            };
+            self.tree.backup(path, v, true);
+        }
+    }
+
+    /// Descends from the root to a leaf, see descend, adding the leaf to the
+    /// tree if its edge was never taken. With pending, the edges taken get a
+    /// pending visit.
     ///
     /// Returns:
-    ///     v: the negative of the value of the current canonicalBoard
-    fn search(&mut self, canonical_board: &Vec<Vec<i8>>) -> f32 {
-        let s = self.game.string_representation(canonical_board);
-
-        if !self.es.contains_key(&s) {
-            self.es
-                .insert(s.clone(), self.game.get_game_ended(canonical_board, 1));
-        }
-        if *self.es.get(&s).unwrap() != 0 as i8 {
-            // terminal node
-            return -self.es.get(&s).unwrap() as f32;
-        }
-
-        if !self.ps.contains_key(&s) {
-            // leaf node
-            let output = self.nnet.predict(canonical_board);
-            let pi = output.0;
-            let v = output.1;
-            let valids = self.game.get_valid_moves(canonical_board, 1);
-            // a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
-            let mut pi = pi
-                .iter()
-                .zip(valids.iter())
-                .map(|(x, y)| x * *y as f32)
-                .collect::<Vec<f32>>();
-            let sum_ps_s = pi.iter().sum::<f32>();
-            if sum_ps_s > 0.0 {
-                for i in pi.iter_mut() {
-                    *i /= sum_ps_s;
-                }
-            } else {
-                // if all valid moves were masked make all valid moves equally probable
-
-                // NB! All valid moves may be masked if either your NNet architecture is insufficient or you've get overfitting or something else.
-                // If you have got dozens or hundreds of these messages you should pay attention to your NNet and/or training process.
-                println!("All valid moves were masked, doing a workaround.");
-                pi = pi
-                    .iter()
-                    .zip(valids.iter())
-                    .map(|(x, y)| x + *y as f32)
-                    .collect::<Vec<f32>>();
-                let sum = pi.iter().sum::<f32>();
-                for i in pi.iter_mut() {
-                    *i /= sum;
-                }
+    ///     path: the (node, edge) taken at every step
+    ///     leaf: the node the descent stopped at
+    ///     board: the canonical board of leaf
+    fn descend(
+        &mut self,
+        canonical_board: &G::State,
+        pending: bool,
+    ) -> (Vec<(NodeId, EdgeId)>, NodeId, G::State) {
+        let mut board = canonical_board.clone();
+        let mut path = Vec::new();
+        let settings = self.settings();
+        let leaf = match descend(
+            &self.tree, &self.game, &mut board, settings, pending, &mut path,
+        ) {
+            Descent::Leaf(leaf) => leaf,
+            Descent::New(edge, s) => {
+                let result = self.game.get_game_ended(&board, 1);
+                self.tree
+                    .add_child(edge, s, result, || self.game.string_representation(&board))
             }
+        };
+        (path, leaf, board)
+    }
 
-            self.ps.insert(s.clone(), pi);
-            self.vs.insert(s.clone(), valids);
-            self.ns.insert(s.clone(), 0);
-            return -v;
-        }
-
-        let valids = self.vs.get(&s).unwrap();
-        let mut cur_best = std::f32::NEG_INFINITY;
-        let mut best_act = -1;
-
-        // pick the action with the highest upper confidence bound
-        for a in 0..self.game.get_action_size() {
-            if *valids.get(a).unwrap() > 0 {
-                let qsa_key = (s.clone(), a.clone());
-                let cpuct = self.args.get("cpuct").unwrap().parse::<f32>().unwrap();
-                let ps_value = self.ps.get(&s).unwrap().get(a).unwrap();
-                let sqrt_ns = f32::sqrt(*self.ns.get(&s).unwrap() as f32);
-                let eps = 1e-8;
-                let u;
-                if self.qsa.contains_key(&qsa_key) {
-                    let qsa_value = self.qsa.get(&qsa_key).unwrap();
-                    let nsa_value = self.nsa.get(&qsa_key).unwrap();
-                    u = qsa_value + cpuct * ps_value * sqrt_ns / (1.0 + nsa_value);
-                } else {
-                    u = cpuct * ps_value * f32::sqrt(*self.ns.get(&s).unwrap() as f32 + eps as f32);
-                }
-                if u > cur_best {
-                    cur_best = u;
-                    best_act = a as isize;
-                }
-            }
+    /// Evaluates canonicalBoards with the network in one batch, on their
+    /// canonical symmetry when canonicalSymmetry is set.
+    fn evaluate(&self, canonical_boards: &[&G::State]) -> Vec<(Vec<f32>, f32)> {
+        if !self.canonical_symmetry {
+            return self.nnet.predict_batch(canonical_boards);
         }
+        // symmetrical positions get the same evaluation
+        let symmetries: Vec<(G::State, usize)> = canonical_boards
+            .iter()
+            .map(|board| self.game.get_canonical_symmetry(board))
+            .collect();
+        let boards: Vec<&G::State> = symmetries.iter().map(|(board, _)| board).collect();
+        self.nnet
+            .predict_batch(&boards)
+            .into_iter()
+            .zip(&symmetries)
+            .map(|((pi, v), (_, symmetry))| (self.game.policy_from_symmetry(*symmetry, &pi), v))
+            .collect()
+    }
 
-        let a = best_act;
-        let next_state = self.game.get_next_state(canonical_board, 1, a as u8);
-        let next_s = self.game.get_canonical_form(&next_state.0, next_state.1);
+    /// Adds the edges of leaf with the policy pi of the network, masked to
+    /// the valid moves.
+    fn expand(&mut self, leaf: NodeId, canonical_board: &G::State, pi: Vec<f32>) {
+        let valids = self.game.get_valid_moves(canonical_board, 1);
+        self.tree.expand(leaf, &mask_policy(&pi, &valids), &valids);
+    }
+}
 
-        let v = self.search(&next_s);
+/// Where a descent stopped.
+enum Descent {
+    /// A node that is terminal or not expanded yet.
+    Leaf(NodeId),
+    /// An edge that was never taken, and the hash of its board.
+    New(EdgeId, u64),
+}
 
-        let qsa_key = (s.clone(), a as usize);
-        if self.qsa.contains_key(&qsa_key) {
-            let nsa_value = self.nsa.get(&qsa_key).unwrap();
-            let qsa_value = self.qsa.get(&qsa_key).unwrap();
-            let new_qsa_value = (nsa_value * qsa_value + v) / (nsa_value + 1.0);
-            self.qsa.insert(qsa_key.clone(), new_qsa_value);
-            if let Some(x) = self.nsa.get_mut(&qsa_key) {
-                *x += 1.0;
-            }
-        } else {
-            self.qsa.insert(qsa_key.clone(), v);
-            self.nsa.insert(qsa_key.clone(), 1.0);
+/// Descends from the root along the edges with the highest upper confidence
+/// bound, pushing every (node, edge) taken to path and playing it on board,
+/// which starts as the canonical board of the root. With pending, every edge
+/// taken gets a pending visit.
+fn descend<G: Game>(
+    tree: &Tree,
+    game: &G,
+    board: &mut G::State,
+    settings: Settings,
+    pending: bool,
+    path: &mut Vec<(NodeId, EdgeId)>,
+) -> Descent {
+    let mut node = ROOT;
+    loop {
+        if tree.node(node).result.is_some() || !tree.is_expanded(node) {
+            return Descent::Leaf(node);
+        }
+        let edge = tree.select(node, settings.cpuct, settings.virtual_loss);
+        if pending {
+            tree.add_pending(edge);
         }
+        path.push((node, edge));
+        let (next_board, next_s) = child(game, board, tree.node(node).s, tree.edge(edge).action);
+        *board = next_board;
+        match tree.edge(edge).child() {
+            Some(child) => node = child,
+            None => return Descent::New(edge, next_s),
+        }
+    }
+}
+
+/// Returns pi masked to the valid moves and normalized.
+fn mask_policy(pi: &[f32], valids: &[u8]) -> Vec<f32> {
+    // a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
+    let mut pi = pi
+        .iter()
+        .zip(valids.iter())
+        .map(|(x, y)| x * *y as f32)
+        .collect::<Vec<f32>>();
+    let sum_ps_s = pi.iter().sum::<f32>();
+    if sum_ps_s > 0.0 {
+        for i in pi.iter_mut() {
+            *i /= sum_ps_s;
+        }
+    } else {
+        // if all valid moves were masked make all valid moves equally probable
 
-        if let Some(x) = self.ns.get_mut(&s) {
-            *x += 1;
+        // NB! All valid moves may be masked if either your NNet architecture is insufficient or you've get overfitting or something else.
+        // If you have got dozens or hundreds of these messages you should pay attention to your NNet and/or training process.
+        println!("All valid moves were masked, doing a workaround.");
+        pi = pi
+            .iter()
+            .zip(valids.iter())
+            .map(|(x, y)| x + *y as f32)
+            .collect::<Vec<f32>>();
+        let sum = pi.iter().sum::<f32>();
+        for i in pi.iter_mut() {
+            *i /= sum;
         }
-        return -v;
     }
+    pi
 }
+
+/// Returns the canonical form of the board after action a of canonicalBoard,
+/// and its hash.
+fn child<G: Game>(game: &G, canonical_board: &G::State, s: u64, a: u16) -> (G::State, u64) {
+    let action = game.index_to_action(a as usize);
+    let next_state = game.get_next_state_hashed(canonical_board, 1, action, s);
+    let next_board = game.get_canonical_form(&next_state.0, next_state.1);
+    let next_s = game.get_canonical_hash(next_state.2, next_state.1);
+    (next_board, next_s)
+}
+
+mod budget;
+mod parallel;
+mod tree;
+
+pub use parallel::bench_command;
+
+#[cfg(test)]
+mod tests;
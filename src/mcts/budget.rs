@@ -0,0 +1,165 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use super::tree::{Tree, ROOT};
+
+/// Limits of a search, none by default. The search stops at the first limit
+/// it reaches, after at least one simulation, so that the root is expanded.
+#[derive(Clone, Debug, Default)]
+pub struct SearchBudget {
+    /// Number of simulations.
+    pub simulations: Option<usize>,
+    /// Time by which the search should be done.
+    pub deadline: Option<Instant>,
+    /// Time the search may take from its start, for time controls that give
+    /// every move the same time.
+    pub time: Option<Duration>,
+    /// Number of nodes of the tree, counting those kept from earlier searches.
+    pub nodes: Option<usize>,
+    /// Bytes of the tree, see MCTS::memory.
+    pub memory: Option<usize>,
+    /// Set by the caller, possibly from another thread, to stop the search.
+    pub stop: Option<Arc<AtomicBool>>,
+    /// Stop once the most visited move of the root cannot be overtaken in the
+    /// simulations left, as far as the simulations and the deadline tell.
+    pub early_stop: bool,
+}
+
+/// Why a search stopped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    Simulations,
+    Deadline,
+    Nodes,
+    Memory,
+    Signal,
+    /// The best move could no longer change.
+    Decided,
+}
+
+/// What a search did.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SearchReport {
+    pub simulations: usize,
+    pub elapsed: Duration,
+    pub reason: StopReason,
+}
+
+impl SearchBudget {
+    /// The budget that the args of MCTS set: numMCTSSims simulations, and
+    /// optionally a time of searchTimeMs, searchNodes and searchMemoryMb (0
+    /// for no limit) and earlyStop.
+    pub fn from_args(args: &HashMap<String, String>) -> Self {
+        let limit = |key: &str| {
+            args.get(key)
+                .map(|v| v.parse::<usize>().unwrap())
+                .filter(|v| *v > 0)
+        };
+        SearchBudget {
+            simulations: limit("numMCTSSims"),
+            time: limit("searchTimeMs").map(|ms| Duration::from_millis(ms as u64)),
+            nodes: limit("searchNodes"),
+            memory: limit("searchMemoryMb").map(|mb| mb * 1024 * 1024),
+            early_stop: args
+                .get("earlyStop")
+                .is_some_and(|v| v.parse::<bool>().unwrap()),
+            ..Default::default()
+        }
+    }
+
+    /// Whether the budget has a limit that ends a search: simulations, a
+    /// deadline or time, nodes, memory or a stop signal. Early stop alone
+    /// needs the simulations or the deadline to tell what is left.
+    pub fn is_limited(&self) -> bool {
+        self.simulations.is_some()
+            || self.deadline.is_some()
+            || self.time.is_some()
+            || self.nodes.is_some()
+            || self.memory.is_some()
+            || self.stop.is_some()
+    }
+
+    /// Returns why a search that started at start and has run (or started)
+    /// done simulations on tree should stop, if it should. The simulations
+    /// are checked by the search itself, since its threads claim them.
+    pub(super) fn check(&self, tree: &Tree, done: usize, start: Instant) -> Option<StopReason> {
+        if self
+            .stop
+            .as_ref()
+            .is_some_and(|stop| stop.load(Ordering::Relaxed))
+        {
+            return Some(StopReason::Signal);
+        }
+        let now = Instant::now();
+        let deadline = self.deadline(start);
+        if deadline.is_some_and(|deadline| now >= deadline) {
+            return Some(StopReason::Deadline);
+        }
+        let memory = tree.memory();
+        if self.nodes.is_some_and(|nodes| memory.nodes >= nodes) {
+            return Some(StopReason::Nodes);
+        }
+        if self.memory.is_some_and(|bytes| memory.bytes >= bytes) {
+            return Some(StopReason::Memory);
+        }
+        if self.early_stop && self.is_decided(tree, done, deadline, now - start, now) {
+            return Some(StopReason::Decided);
+        }
+        None
+    }
+
+    /// The earlier of the deadline and the end of the time of a search that
+    /// started at start.
+    fn deadline(&self, start: Instant) -> Option<Instant> {
+        let end = self.time.map(|time| start + time);
+        match (self.deadline, end) {
+            (Some(deadline), Some(end)) => Some(deadline.min(end)),
+            (deadline, end) => deadline.or(end),
+        }
+    }
+
+    /// Whether the second most visited move of the root is behind the most
+    /// visited one by more than the simulations left: those of the budget,
+    /// and those the rate so far allows before the deadline.
+    fn is_decided(
+        &self,
+        tree: &Tree,
+        done: usize,
+        deadline: Option<Instant>,
+        elapsed: Duration,
+        now: Instant,
+    ) -> bool {
+        if tree.is_empty() || !tree.is_expanded(ROOT) {
+            return false;
+        }
+        let mut left = self
+            .simulations
+            .map(|sims| sims.saturating_sub(done) as f32);
+        if let Some(deadline) = deadline {
+            let rate = done as f32 / elapsed.as_secs_f32().max(1e-6);
+            let by_deadline = rate * (deadline - now).as_secs_f32();
+            left = Some(left.map_or(by_deadline, |left| left.min(by_deadline)));
+        }
+        let Some(left) = left else {
+            return false;
+        };
+        let (mut best, mut second) = (0, 0);
+        for edge in tree.edges(ROOT) {
+            let visits = edge.visits();
+            if visits > best {
+                (best, second) = (visits, best);
+            } else if visits > second {
+                second = visits;
+            }
+        }
+        // with a single valid move, there is nothing to decide once it has a
+        // visit, which the counts of the search need
+        best > 0 && (tree.edges(ROOT).len() == 1 || (best - second) as f32 > left)
+    }
+}
@@ -0,0 +1,208 @@
+use std::{
+    collections::HashMap,
+    mem,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, OnceLock, RwLock,
+    },
+    thread,
+    time::Instant,
+};
+
+use burn::tensor::backend::AutodiffBackend;
+
+use super::{
+    budget::{SearchBudget, StopReason},
+    descend, mask_policy,
+    tree::Tree,
+    Descent, Settings, MCTS,
+};
+use crate::{game::Game, n_net::NNetWrapper, neural_net::NeuralNet, othello::Othello};
+
+/// Performs one simulation from canonicalBoard on the tree that the threads
+/// of a parallel search share, like MCTS::search with pending visits. The
+/// statistics are atomics, so that descents and backups only take the read
+/// lock; adding a node or the edges of a leaf takes the write lock, and
+/// evaluating a leaf no lock at all.
+fn simulate<G: Game>(
+    tree: &RwLock<Tree>,
+    game: &G,
+    canonical_board: &G::State,
+    settings: Settings,
+    evaluator: &Evaluator<G>,
+) {
+    let mut board = canonical_board.clone();
+    let mut path = Vec::new();
+    let descent = descend(
+        &tree.read().unwrap(),
+        game,
+        &mut board,
+        settings,
+        true,
+        &mut path,
+    );
+    let leaf = match descent {
+        Descent::Leaf(leaf) => leaf,
+        Descent::New(edge, s) => {
+            let result = game.get_game_ended(&board, 1);
+            tree.write()
+                .unwrap()
+                .add_child(edge, s, result, || game.string_representation(&board))
+        }
+    };
+    let result = tree.read().unwrap().node(leaf).result;
+    let v = match result {
+        Some(result) => -result.value(settings.draw_value),
+        None => {
+            // another thread may expand the leaf while this one waits for the
+            // network, in which case its expansion is kept
+            let (pi, v) = evaluator.evaluate(board.clone());
+            let valids = game.get_valid_moves(&board, 1);
+            tree.write()
+                .unwrap()
+                .expand(leaf, &mask_policy(&pi, &valids), &valids);
+            -v
+        }
+    };
+    tree.read().unwrap().backup(&path, v, true);
+}
+
+/// A board that a thread of a parallel search waits to have evaluated.
+struct Request<G: Game> {
+    board: G::State,
+    reply: mpsc::Sender<(Vec<f32>, f32)>,
+}
+
+/// The network as the threads of a parallel search see it: every thread sends
+/// its leaves to the thread that started the search, which evaluates the
+/// leaves of all waiting threads in one batch.
+struct Evaluator<G: Game> {
+    requests: mpsc::Sender<Request<G>>,
+}
+
+impl<G: Game> Evaluator<G> {
+    fn evaluate(&self, board: G::State) -> (Vec<f32>, f32) {
+        let (reply, prediction) = mpsc::channel();
+        self.requests
+            .send(Request { board, reply })
+            .expect("the search should evaluate leaves until its threads finish");
+        prediction
+            .recv()
+            .expect("the search should evaluate every leaf")
+    }
+}
+
+impl<G: Game, B: AutodiffBackend, N: NeuralNet<B, G>> MCTS<G, B, N> {
+    /// Performs simulations from canonicalBoard on searchThreads threads
+    /// sharing one tree, until budget runs out for a search that started at
+    /// start and has done simulations so far. The threads take virtual losses
+    /// on the edges they descend, so that they spread over the tree, and the
+    /// network runs on the calling thread, evaluating the leaves of all
+    /// waiting threads in one batch.
+    ///
+    /// Returns:
+    ///     done: the simulations of the search, including the earlier ones
+    ///     reason: why the threads stopped
+    pub(super) fn search_parallel(
+        &mut self,
+        canonical_board: &G::State,
+        budget: &SearchBudget,
+        start: Instant,
+        done: usize,
+    ) -> (usize, StopReason) {
+        let tree = RwLock::new(mem::take(&mut self.tree));
+        let settings = self.settings();
+        // simulations started, which the budget of simulations bounds, and done
+        let started = AtomicUsize::new(done);
+        let done = AtomicUsize::new(done);
+        let stopped = OnceLock::new();
+        let (requests, received) = mpsc::channel::<Request<G>>();
+        let game = &self.game;
+        thread::scope(|scope| {
+            for _ in 0..self.threads {
+                let evaluator = Evaluator {
+                    requests: requests.clone(),
+                };
+                let board = canonical_board.clone();
+                let (tree, started, done, stopped) = (&tree, &started, &done, &stopped);
+                scope.spawn(move || loop {
+                    let i = started.fetch_add(1, Ordering::Relaxed);
+                    let reason = if budget.simulations.is_some_and(|sims| i >= sims) {
+                        Some(StopReason::Simulations)
+                    } else {
+                        let done = done.load(Ordering::Relaxed);
+                        budget.check(&tree.read().unwrap(), done, start)
+                    };
+                    if let Some(reason) = reason {
+                        // the first thread to stop tells why
+                        let _ = stopped.set(reason);
+                    }
+                    if stopped.get().is_some() {
+                        break;
+                    }
+                    simulate(tree, game, &board, settings, &evaluator);
+                    done.fetch_add(1, Ordering::Relaxed);
+                });
+            }
+            // the threads hold the only senders, so that the loop ends with them
+            drop(requests);
+            while let Ok(request) = received.recv() {
+                let mut batch = vec![request];
+                batch.extend(received.try_iter());
+                let boards: Vec<&G::State> = batch.iter().map(|r| &r.board).collect();
+                for (request, prediction) in batch.iter().zip(self.evaluate(&boards)) {
+                    // the thread is gone only if it panicked, which the scope reports
+                    let _ = request.reply.send(prediction);
+                }
+            }
+        });
+        self.tree = tree.into_inner().unwrap();
+        let reason = stopped
+            .into_inner()
+            .expect("every thread stops for a reason");
+        (done.into_inner(), reason)
+    }
+}
+
+/// Runs `othello bench [board size | position] [simulations] [threads]`,
+/// timing searches of simulations (800 by default) from the position with an
+/// untrained network on device, on 1, 2, 4... threads up to the given count
+/// (all available cores by default). Prints the simulations per second of
+/// every thread count, the speedup over one thread and the size of the tree.
+pub fn bench_command<B: AutodiffBackend>(args: &[String], device: B::Device) {
+    let (game, board, player) =
+        match Othello::from_command_arg(args.first().map_or("8", |v| v.as_str())) {
+            Ok(position) => position,
+            Err(e) => return println!("Invalid position: {e}"),
+        };
+    let sims = args.get(1).map_or(800, |v| {
+        v.parse::<usize>().expect("simulations should be a number")
+    });
+    let max_threads = args.get(2).map_or_else(
+        || thread::available_parallelism().map_or(1, |n| n.get()),
+        |v| v.parse::<usize>().expect("threads should be a number"),
+    );
+    let nnet: NNetWrapper<B, Othello> = NNetWrapper::new(game.clone(), device);
+    let board = game.get_canonical_form(&board, player);
+
+    let mut one_thread = None;
+    let mut threads = 1;
+    while threads <= max_threads {
+        let mut args = HashMap::new();
+        args.insert("cpuct".to_owned(), "1".to_owned());
+        args.insert("searchThreads".to_owned(), threads.to_string());
+        let mut mcts = MCTS::new(game.clone(), nnet.clone(), args);
+        let budget = SearchBudget {
+            simulations: Some(sims),
+            ..Default::default()
+        };
+        let (_, report) = mcts.get_action_prob_within(&board, 1, &budget);
+        let rate = report.simulations as f32 / report.elapsed.as_secs_f32();
+        let speedup = rate / *one_thread.get_or_insert(rate);
+        println!(
+            "{threads} threads: {rate:.0} simulations per second ({speedup:.2}x), tree of {}",
+            mcts.memory()
+        );
+        threads *= 2;
+    }
+}
@@ -0,0 +1,620 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use burn::{
+    backend::{Autodiff, LibTorch},
+    tensor::backend::AutodiffBackend,
+};
+
+use super::{
+    mix_dirichlet_noise,
+    tree::{EdgeId, Tree, ROOT},
+    SearchBudget, StopReason, MCTS,
+};
+use crate::{
+    game::{Example, Game},
+    mnk_game::MnkGame,
+    neural_net::NeuralNet,
+};
+
+type TestBackend = Autodiff<LibTorch>;
+
+/// A network without parameters that predicts a uniform policy and a value of
+/// zero, so that the search alone decides the moves.
+struct UniformNet<G: Game> {
+    game: G,
+}
+
+impl<B: AutodiffBackend, G: Game> NeuralNet<B, G> for UniformNet<G> {
+    fn new(game: G, _device: B::Device) -> Self {
+        UniformNet { game }
+    }
+
+    fn train(&self, _examples: &[Example<G>]) {}
+
+    fn predict(&self, _board: &G::State) -> (Vec<f32>, f32) {
+        let action_size = self.game.get_action_size();
+        (vec![1. / action_size as f32; action_size], 0.)
+    }
+
+    fn save_checkpoint(&self, _folder: &str, _filename: &str) {}
+
+    fn load_checkpoint(&mut self, _folder: &str, _filename: &str) {}
+}
+
+/// A network that puts the whole policy on the first empty square, which
+/// tells the symmetries of a board apart.
+struct FirstEmptyNet {
+    game: MnkGame,
+}
+
+impl<B: AutodiffBackend> NeuralNet<B, MnkGame> for FirstEmptyNet {
+    fn new(game: MnkGame, _device: B::Device) -> Self {
+        FirstEmptyNet { game }
+    }
+
+    fn train(&self, _examples: &[Example<MnkGame>]) {}
+
+    fn predict(&self, board: &Vec<Vec<i8>>) -> (Vec<f32>, f32) {
+        let mut pi = vec![0.; self.game.get_action_size()];
+        let first = board.iter().flatten().position(|s| *s == 0).unwrap();
+        pi[first] = 1.;
+        (pi, 0.)
+    }
+
+    fn save_checkpoint(&self, _folder: &str, _filename: &str) {}
+
+    fn load_checkpoint(&mut self, _folder: &str, _filename: &str) {}
+}
+
+fn tictactoe_mcts(sims: usize) -> MCTS<MnkGame, TestBackend, UniformNet<MnkGame>> {
+    let game = MnkGame::new(3);
+    let mut args = HashMap::new();
+    args.insert("numMCTSSims".to_owned(), sims.to_string());
+    args.insert("cpuct".to_owned(), "1".to_owned());
+    MCTS::new(game.clone(), UniformNet { game }, args)
+}
+
+fn batched_tictactoe_mcts(
+    sims: usize,
+    leaf_batch_size: usize,
+) -> MCTS<MnkGame, TestBackend, UniformNet<MnkGame>> {
+    let mut mcts = tictactoe_mcts(sims);
+    mcts.leaf_batch_size = leaf_batch_size;
+    mcts
+}
+
+fn parallel_tictactoe_mcts(
+    sims: usize,
+    threads: usize,
+) -> MCTS<MnkGame, TestBackend, UniformNet<MnkGame>> {
+    let mut mcts = tictactoe_mcts(sims);
+    mcts.threads = threads;
+    mcts
+}
+
+/// Returns the prior of every action of the root, zero for invalid actions.
+fn root_priors(tree: &Tree) -> Vec<f32> {
+    let mut priors = vec![0.; 9];
+    for edge in tree.edges(ROOT) {
+        priors[edge.action as usize] = edge.prior;
+    }
+    priors
+}
+
+/// Returns the visits of the edges of the root.
+fn root_edge_visits(tree: &Tree) -> Vec<u32> {
+    tree.edges(ROOT).iter().map(|e| e.visits()).collect()
+}
+
+/// Checks that every node was visited as often as its edges, and that no
+/// visit is left pending.
+fn assert_consistent(tree: &Tree) {
+    for node in 0..tree.memory().nodes as u32 {
+        let edges = tree.edges(node);
+        assert_eq!(
+            edges.iter().map(|e| e.visits()).sum::<u32>(),
+            tree.node(node).visits()
+        );
+        assert!(edges.iter().all(|e| e.pending() == 0));
+    }
+}
+
+/// Plays the squares in order from the initial board, starting with player 1.
+fn play(game: &MnkGame, squares: &[usize]) -> (Vec<Vec<i8>>, i8) {
+    let mut board = game.get_init_board().clone();
+    let mut player = 1;
+    for square in squares {
+        (board, player) = game.get_next_state(&board, player, *square);
+    }
+    (board, player)
+}
+
+fn best_action(
+    mcts: &mut MCTS<MnkGame, TestBackend, UniformNet<MnkGame>>,
+    board: &Vec<Vec<i8>>,
+) -> usize {
+    let pi = mcts.get_action_prob(board, 0);
+    pi.iter().position(|p| *p == 1.).unwrap()
+}
+
+#[test]
+fn tictactoe_takes_the_win() {
+    let mut mcts = tictactoe_mcts(200);
+    let game = MnkGame::new(3);
+    // X on a1 and b1, O on a2 and b2: c1 wins, while c2 would lose
+    let (board, player) = play(&game, &[0, 3, 1, 4]);
+    assert_eq!(player, 1);
+    assert_eq!(best_action(&mut mcts, &board), 2);
+}
+
+#[test]
+fn tictactoe_blocks_the_loss() {
+    let mut mcts = tictactoe_mcts(400);
+    let game = MnkGame::new(3);
+    // X on a1 and c3, O on b2 and a2: X has to block on c2
+    let (board, player) = play(&game, &[0, 4, 8, 3]);
+    assert_eq!(player, 1);
+    assert_eq!(best_action(&mut mcts, &board), 5);
+}
+
+#[test]
+fn tictactoe_self_play_is_a_draw() {
+    // perfect play draws tic-tac-toe, and enough simulations find it; the
+    // tree does not share the visits of transpositions, hence the count
+    let game = MnkGame::new(3);
+    let mut mcts = tictactoe_mcts(1600);
+    let mut board = game.get_init_board().clone();
+    let mut player = 1;
+    while game.get_game_ended(&board, player).is_none() {
+        let canon = game.get_canonical_form(&board, player);
+        let action = best_action(&mut mcts, &canon);
+        (board, player) = game.get_next_state(&board, player, action);
+    }
+    assert_eq!(game.get_game_ended(&board, player).unwrap().margin, 0);
+}
+
+#[test]
+fn leaves_are_evaluated_on_their_canonical_symmetry() {
+    let game = MnkGame::new(3);
+    // X on a1: the canonical symmetry has X on another corner
+    let (board, _) = play(&game, &[0]);
+    let board = game.get_canonical_form(&board, -1);
+    let (canonical, symmetry) = game.get_canonical_symmetry(&board);
+    assert_ne!(canonical, board);
+
+    for canonical_symmetry in [false, true] {
+        let mut args = HashMap::new();
+        args.insert("numMCTSSims".to_owned(), "1".to_owned());
+        args.insert("cpuct".to_owned(), "1".to_owned());
+        args.insert(
+            "canonicalSymmetry".to_owned(),
+            canonical_symmetry.to_string(),
+        );
+        let net = FirstEmptyNet { game: game.clone() };
+        let mut mcts: MCTS<MnkGame, TestBackend, FirstEmptyNet> =
+            MCTS::new(game.clone(), net, args);
+        mcts.get_action_prob(&board, 1);
+        let ps = root_priors(&mcts.tree);
+        let first = if canonical_symmetry {
+            let mut pi = vec![0.; 9];
+            pi[canonical.iter().flatten().position(|s| *s == 0).unwrap()] = 1.;
+            game.policy_from_symmetry(symmetry, &pi)
+        } else {
+            vec![0., 1., 0., 0., 0., 0., 0., 0., 0.]
+        };
+        assert_eq!(ps, first);
+    }
+}
+
+#[test]
+fn dirichlet_noise_sums_to_one_over_valid_moves() {
+    let game = MnkGame::new(3);
+    let (board, _) = play(&game, &[0, 4, 8]);
+    let valids = game.get_valid_moves(&board, 1);
+    let legal = valids.iter().filter(|v| **v == 1).count() as f32;
+    let priors: Vec<f32> = valids.iter().map(|v| *v as f32 / legal).collect();
+    let mut rng = rand::thread_rng();
+    for alpha in [0.03, 0.3, 10.] {
+        let noisy = mix_dirichlet_noise(&priors, &valids, alpha, 0.25, &mut rng);
+        assert!((noisy.iter().sum::<f32>() - 1.).abs() < 1e-5);
+        for (p, v) in noisy.iter().zip(&valids) {
+            if *v == 0 {
+                assert_eq!(*p, 0.);
+            } else {
+                // the priors keep a weight of 0.75
+                assert!(*p >= 0.75 / legal - 1e-6);
+            }
+        }
+        assert_ne!(noisy, priors);
+    }
+    // without weight, the priors are unchanged
+    assert_eq!(
+        mix_dirichlet_noise(&priors, &valids, 0.3, 0., &mut rng),
+        priors
+    );
+}
+
+#[test]
+fn root_noise_is_drawn_for_each_search() {
+    let game = MnkGame::new(3);
+    let mut mcts = tictactoe_mcts(50).with_root_noise(0.3, 0.25);
+    let board = game.get_init_board().clone();
+    let pi = mcts.get_action_prob(&board, 1);
+    assert!((pi.iter().sum::<f32>() - 1.).abs() < 1e-5);
+    // the tree keeps the priors of the network
+    assert_eq!(root_priors(&mcts.tree), vec![1. / 9.; 9]);
+}
+
+#[test]
+fn reused_root_is_searched_with_noise() {
+    let game = MnkGame::new(3);
+    let board = game.get_init_board().clone();
+    let first_actions: HashSet<u16> = (0..20)
+        .map(|_| {
+            // pure noise, so that the uniform priors do not decide the descent
+            let mut mcts = tictactoe_mcts(1).with_root_noise(0.3, 1.);
+            // the first search only expands the root
+            mcts.get_action_prob(&board, 1);
+            mcts.get_action_prob(&board, 1);
+            let visited: Vec<u16> = mcts
+                .tree
+                .edges(ROOT)
+                .iter()
+                .filter(|e| e.visits() > 0)
+                .map(|e| e.action)
+                .collect();
+            assert_eq!(visited.len(), 1);
+            visited[0]
+        })
+        .collect();
+    // without noise, the ties of the uniform priors go to the first move
+    assert!(first_actions.len() > 1);
+}
+
+#[test]
+fn reroot_keeps_the_subtree_of_the_played_move() {
+    let game = MnkGame::new(3);
+    let mut mcts = tictactoe_mcts(300);
+    let board = game.get_init_board().clone();
+    let pi = mcts.get_action_prob(&board, 1);
+    let a = (0..9).max_by(|a, b| pi[*a].total_cmp(&pi[*b])).unwrap();
+    let (next_board, next_player) = game.get_next_state(&board, 1, a);
+    let child = game.get_canonical_form(&next_board, next_player);
+    let edge = mcts
+        .tree
+        .edges(ROOT)
+        .iter()
+        .find(|e| e.action as usize == a)
+        .unwrap();
+    let child_visits = mcts.tree.node(edge.child().unwrap()).visits();
+    let nodes = mcts.memory().nodes;
+
+    mcts.reroot(&child);
+    assert_eq!(mcts.tree.node(ROOT).s, game.zobrist_hash(&child));
+    assert_eq!(mcts.tree.node(ROOT).visits(), child_visits);
+    assert!(mcts.memory().nodes < nodes);
+    assert_consistent(&mcts.tree);
+
+    // the next search carries on from the visits of the child
+    mcts.get_action_prob(&child, 1);
+    assert_eq!(mcts.tree.node(ROOT).visits(), child_visits + 300);
+}
+
+#[test]
+fn search_without_reuse_starts_anew() {
+    let game = MnkGame::new(3);
+    let board = game.get_init_board().clone();
+    for (reuse, visits) in [(true, 199), (false, 99)] {
+        let mut mcts = tictactoe_mcts(100).with_tree_reuse(reuse);
+        mcts.get_action_prob(&board, 1);
+        mcts.get_action_prob(&board, 1);
+        assert_eq!(mcts.tree.node(ROOT).visits(), visits);
+    }
+}
+
+#[test]
+fn batched_search_takes_the_win() {
+    let game = MnkGame::new(3);
+    let (board, _) = play(&game, &[0, 3, 1, 4]);
+    for leaf_batch_size in [4, 16] {
+        let mut mcts = batched_tictactoe_mcts(200, leaf_batch_size);
+        assert_eq!(best_action(&mut mcts, &board), 2);
+    }
+}
+
+#[test]
+fn batched_search_counts_every_simulation() {
+    let game = MnkGame::new(3);
+    let board = game.get_init_board().clone();
+    let mut mcts = batched_tictactoe_mcts(100, 8);
+    mcts.get_action_prob(&board, 1);
+    // the first simulation expands the root
+    assert_eq!(mcts.tree.node(ROOT).visits(), 99);
+    assert_consistent(&mcts.tree);
+}
+
+#[test]
+fn batched_visits_are_close_to_sequential() {
+    let game = MnkGame::new(3);
+    // X has to block on c2, and the other moves lose
+    let (board, _) = play(&game, &[0, 4, 8, 3]);
+    let sequential = tictactoe_mcts(1600).get_action_prob(&board, 1);
+    for leaf_batch_size in [8, 32] {
+        let batched = batched_tictactoe_mcts(1600, leaf_batch_size).get_action_prob(&board, 1);
+        let distance: f32 = sequential
+            .iter()
+            .zip(&batched)
+            .map(|(p, q)| (p - q).abs())
+            .sum::<f32>()
+            / 2.;
+        assert!(distance < 0.1, "{sequential:?} and {batched:?}");
+    }
+}
+
+#[test]
+fn virtual_loss_spreads_a_batch_over_leaves() {
+    let game = MnkGame::new(3);
+    let board = game.get_init_board().clone();
+    let mut mcts = batched_tictactoe_mcts(9, 8);
+    mcts.get_action_prob(&board, 1);
+    // the eight descents after the root expansion take distinct moves
+    let visits = root_edge_visits(&mcts.tree);
+    assert_eq!(visits.iter().filter(|n| **n == 1).count(), 8);
+}
+
+#[test]
+fn pending_visits_count_once_with_their_loss() {
+    // a root of two moves of equal priors, visited with the given values
+    let tree = |first: &[f32], second: &[f32]| {
+        let mut tree = Tree::new(false);
+        tree.add_node(0, None, String::new);
+        tree.expand(ROOT, &[0.5, 0.5], &[1, 1]);
+        for (edge, values) in [first, second].iter().enumerate() {
+            for v in *values {
+                tree.backup(&[(ROOT, edge as EdgeId)], *v, false);
+            }
+        }
+        tree
+    };
+
+    let even = tree(&[0.], &[0.]);
+    // the ties go to the first move
+    assert_eq!(even.select(ROOT, 1., 0.), 0);
+    // without a loss, a pending visit still counts as a visit, which lowers
+    // the exploration term of its move
+    even.add_pending(0);
+    assert_eq!(even.select(ROOT, 1., 0.), 1);
+
+    let winning = tree(&[1., 1., 1.], &[0.]);
+    winning.add_pending(0);
+    // 4 visits of value sum 2, ahead of the second move
+    assert_eq!(winning.select(ROOT, 1., 1.), 0);
+    // 4 visits of value sum 0, behind it
+    assert_eq!(winning.select(ROOT, 1., 3.), 1);
+}
+
+#[test]
+fn parallel_search_takes_the_win() {
+    let game = MnkGame::new(3);
+    let (board, _) = play(&game, &[0, 3, 1, 4]);
+    for threads in [2, 8] {
+        let mut mcts = parallel_tictactoe_mcts(200, threads);
+        assert_eq!(best_action(&mut mcts, &board), 2);
+    }
+}
+
+#[test]
+fn parallel_search_counts_every_simulation() {
+    let game = MnkGame::new(3);
+    let board = game.get_init_board().clone();
+    let mut mcts = parallel_tictactoe_mcts(500, 4);
+    mcts.get_action_prob(&board, 1);
+    assert_eq!(mcts.tree.node(ROOT).visits(), 499);
+    assert_consistent(&mcts.tree);
+
+    // the next search carries on from the same tree
+    mcts.get_action_prob(&board, 1);
+    assert_eq!(mcts.tree.node(ROOT).visits(), 999);
+}
+
+#[test]
+fn parallel_visits_are_close_to_sequential() {
+    let game = MnkGame::new(3);
+    let (board, _) = play(&game, &[0, 4, 8, 3]);
+    let sequential = tictactoe_mcts(1600).get_action_prob(&board, 1);
+    for threads in [2, 8] {
+        let parallel = parallel_tictactoe_mcts(1600, threads).get_action_prob(&board, 1);
+        let distance: f32 = sequential
+            .iter()
+            .zip(&parallel)
+            .map(|(p, q)| (p - q).abs())
+            .sum::<f32>()
+            / 2.;
+        assert!(distance < 0.1, "{sequential:?} and {parallel:?}");
+    }
+}
+
+#[test]
+fn tree_stores_the_valid_moves_of_expanded_nodes() {
+    let game = MnkGame::new(3);
+    let (board, _) = play(&game, &[0, 4, 8]);
+    let board = game.get_canonical_form(&board, -1);
+    let mut mcts = tictactoe_mcts(50);
+    mcts.get_action_prob(&board, 1);
+    let actions: Vec<u16> = mcts.tree.edges(ROOT).iter().map(|e| e.action).collect();
+    assert_eq!(actions, vec![1, 2, 3, 5, 6, 7]);
+    assert_consistent(&mcts.tree);
+
+    // every node but the terminal ones is expanded, once per simulation
+    let memory = mcts.memory();
+    assert!(memory.nodes <= 50);
+    assert!(memory.bytes >= memory.nodes * 16 + memory.edges * 16);
+    mcts.clear();
+    assert_eq!(mcts.memory().nodes, 0);
+}
+
+#[test]
+fn search_reports_its_simulations() {
+    let game = MnkGame::new(3);
+    let board = game.get_init_board().clone();
+    for threads in [1, 4] {
+        let mut mcts = parallel_tictactoe_mcts(100, threads);
+        assert_eq!(mcts.last_search(), None);
+        mcts.get_action_prob(&board, 1);
+        let report = mcts.last_search().unwrap();
+        assert_eq!(report.simulations, 100);
+        assert_eq!(report.reason, StopReason::Simulations);
+    }
+}
+
+#[test]
+fn deadline_stops_the_search() {
+    let game = MnkGame::new(3);
+    let board = game.get_init_board().clone();
+    let budget = SearchBudget {
+        time: Some(Duration::from_millis(50)),
+        ..Default::default()
+    };
+    for threads in [1, 4] {
+        let mut mcts = parallel_tictactoe_mcts(0, threads);
+        let (_, report) = mcts.get_action_prob_within(&board, 1, &budget);
+        assert_eq!(report.reason, StopReason::Deadline);
+        assert!(report.elapsed >= Duration::from_millis(50));
+        assert!(report.simulations > 0);
+        assert_eq!(
+            mcts.tree.node(ROOT).visits() as usize,
+            report.simulations - 1
+        );
+        assert_consistent(&mcts.tree);
+    }
+}
+
+#[test]
+fn tree_limits_stop_the_search() {
+    let game = MnkGame::new(3);
+    let board = game.get_init_board().clone();
+    let mut mcts = tictactoe_mcts(10000);
+    let budget = SearchBudget {
+        nodes: Some(30),
+        ..Default::default()
+    };
+    let (_, report) = mcts.get_action_prob_within(&board, 1, &budget);
+    assert_eq!(report.reason, StopReason::Nodes);
+    // a simulation adds at most one node
+    assert_eq!(mcts.memory().nodes, 30);
+
+    let mut mcts = tictactoe_mcts(10000);
+    let budget = SearchBudget {
+        memory: Some(4096),
+        ..Default::default()
+    };
+    let (_, report) = mcts.get_action_prob_within(&board, 1, &budget);
+    assert_eq!(report.reason, StopReason::Memory);
+    assert!(mcts.memory().bytes >= 4096);
+}
+
+#[test]
+fn stop_signal_ends_the_search() {
+    let game = MnkGame::new(3);
+    let board = game.get_init_board().clone();
+    let stop = Arc::new(AtomicBool::new(true));
+    let budget = SearchBudget {
+        stop: Some(stop.clone()),
+        ..Default::default()
+    };
+    let mut mcts = tictactoe_mcts(0);
+    let (pi, report) = mcts.get_action_prob_within(&board, 1, &budget);
+    // the first simulation runs anyway, so that the root has its moves
+    assert_eq!(report.simulations, 1);
+    assert_eq!(report.reason, StopReason::Signal);
+    assert_eq!(pi.len(), 9);
+
+    // a search without any other limit runs until the signal
+    stop.store(false, Ordering::Relaxed);
+    let mut mcts = parallel_tictactoe_mcts(0, 4);
+    let signal = stop.clone();
+    let stopper = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        signal.store(true, Ordering::Relaxed);
+    });
+    let (_, report) = mcts.get_action_prob_within(&board, 1, &budget);
+    stopper.join().unwrap();
+    assert_eq!(report.reason, StopReason::Signal);
+    assert!(report.elapsed >= Duration::from_millis(50));
+}
+
+#[test]
+fn early_stop_keeps_the_best_move() {
+    let game = MnkGame::new(3);
+    // X has to block on c2
+    let (board, _) = play(&game, &[0, 4, 8, 3]);
+    let budget = SearchBudget {
+        simulations: Some(2000),
+        early_stop: true,
+        ..Default::default()
+    };
+    let mut mcts = tictactoe_mcts(0);
+    let (pi, report) = mcts.get_action_prob_within(&board, 0, &budget);
+    assert_eq!(report.reason, StopReason::Decided);
+    assert!(report.simulations < 2000);
+    assert_eq!(pi[5], 1.);
+    let visits = root_edge_visits(&mcts.tree);
+    let best = *visits.iter().max().unwrap();
+    let second = visits.iter().filter(|v| **v < best).max().unwrap();
+    assert!((best - second) as usize > 2000 - report.simulations);
+
+    // with a single valid move, the search only visits it once
+    let (board, _) = play(&game, &[0, 1, 2, 4, 3, 5, 7, 6]);
+    let (pi, report) = mcts.get_action_prob_within(&board, 0, &budget);
+    assert_eq!(report.simulations, 2);
+    assert_eq!(report.reason, StopReason::Decided);
+    assert_eq!(pi[8], 1.);
+}
+
+#[test]
+fn budget_from_args() {
+    let mut args = HashMap::new();
+    args.insert("numMCTSSims".to_owned(), "25".to_owned());
+    args.insert("searchTimeMs".to_owned(), "0".to_owned());
+    args.insert("searchNodes".to_owned(), "1000".to_owned());
+    args.insert("searchMemoryMb".to_owned(), "2".to_owned());
+    args.insert("earlyStop".to_owned(), "true".to_owned());
+    let budget = SearchBudget::from_args(&args);
+    assert_eq!(budget.simulations, Some(25));
+    assert_eq!(budget.time, None);
+    assert_eq!(budget.nodes, Some(1000));
+    assert_eq!(budget.memory, Some(2 * 1024 * 1024));
+    assert!(budget.early_stop);
+
+    args.insert("searchTimeMs".to_owned(), "300".to_owned());
+    let budget = SearchBudget::from_args(&args);
+    assert_eq!(budget.time, Some(Duration::from_millis(300)));
+    assert!(budget.is_limited());
+
+    // 0 is no limit, and early stop alone does not end a search
+    for key in [
+        "numMCTSSims",
+        "searchTimeMs",
+        "searchNodes",
+        "searchMemoryMb",
+    ] {
+        args.insert(key.to_owned(), "0".to_owned());
+    }
+    assert!(!SearchBudget::from_args(&args).is_limited());
+}
+
+#[test]
+#[should_panic(expected = "the search budget should have a limit")]
+fn search_without_limits_panics() {
+    let game = MnkGame::new(3);
+    let board = game.get_init_board().clone();
+    tictactoe_mcts(0).get_action_prob(&board, 1);
+}
@@ -0,0 +1,359 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt, mem,
+    ops::Range,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use crate::{game::GameResult, zobrist::ZobristMap};
+
+/// Index of a node in a Tree. The root is node 0.
+pub(super) type NodeId = u32;
+/// Index of an edge in a Tree.
+pub(super) type EdgeId = u32;
+
+pub(super) const ROOT: NodeId = 0;
+/// The child of an edge that was never taken.
+const NONE: NodeId = NodeId::MAX;
+
+/// A position of the tree, reached through an edge of its parent.
+pub(super) struct Node {
+    /// Zobrist hash of the canonical board.
+    pub(super) s: u64,
+    /// Result of the game for the player to move, if it ended.
+    pub(super) result: Option<GameResult>,
+    /// Visits through the node, not counting the one that expanded it.
+    visits: AtomicU32,
+    /// Edges of the valid moves, created when the network evaluates the node.
+    edges: Range<EdgeId>,
+}
+
+/// A valid move of a node.
+pub(super) struct Edge {
+    pub(super) action: u16,
+    pub(super) prior: f32,
+    visits: AtomicU32,
+    /// Sum of the values of the visits, as f32 bits.
+    value_sum: AtomicU32,
+    /// Visits of searches that are not backed up yet.
+    pending: AtomicU32,
+    child: NodeId,
+}
+
+impl Node {
+    pub(super) fn visits(&self) -> u32 {
+        self.visits.load(Ordering::Relaxed)
+    }
+
+    fn copy(&self) -> Self {
+        Node {
+            s: self.s,
+            result: self.result,
+            visits: AtomicU32::new(self.visits()),
+            edges: 0..0,
+        }
+    }
+}
+
+impl Edge {
+    pub(super) fn visits(&self) -> u32 {
+        self.visits.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn value_sum(&self) -> f32 {
+        f32::from_bits(self.value_sum.load(Ordering::Relaxed))
+    }
+
+    pub(super) fn pending(&self) -> u32 {
+        self.pending.load(Ordering::Relaxed)
+    }
+
+    /// The node the edge leads to, None if it was never taken.
+    pub(super) fn child(&self) -> Option<NodeId> {
+        (self.child != NONE).then_some(self.child)
+    }
+
+    fn copy(&self, child: NodeId) -> Self {
+        Edge {
+            action: self.action,
+            prior: self.prior,
+            visits: AtomicU32::new(self.visits()),
+            value_sum: AtomicU32::new(self.value_sum.load(Ordering::Relaxed)),
+            pending: AtomicU32::new(self.pending()),
+            child,
+        }
+    }
+}
+
+/// Size of a Tree.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TreeMemory {
+    pub nodes: usize,
+    pub edges: usize,
+    /// Bytes allocated for the nodes and the edges.
+    pub bytes: usize,
+}
+
+impl fmt::Display for TreeMemory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} nodes, {} edges, {} KiB",
+            self.nodes,
+            self.edges,
+            self.bytes.div_ceil(1024)
+        )
+    }
+}
+
+/// The search tree of MCTS: nodes and edges in two arenas, the edges of a
+/// node next to each other. The statistics are atomics so that the threads
+/// of a parallel search update them through a shared reference, while adding
+/// nodes or edges takes a mutable one.
+#[derive(Default)]
+pub(super) struct Tree {
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+    // string representation of every hashed board, only kept when checking for collisions
+    seen: Option<ZobristMap<u64, String>>,
+}
+
+impl Tree {
+    pub(super) fn new(check_collisions: bool) -> Self {
+        Tree {
+            seen: check_collisions.then(ZobristMap::default),
+            ..Default::default()
+        }
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub(super) fn node(&self, node: NodeId) -> &Node {
+        &self.nodes[node as usize]
+    }
+
+    pub(super) fn edge(&self, edge: EdgeId) -> &Edge {
+        &self.edges[edge as usize]
+    }
+
+    pub(super) fn edges(&self, node: NodeId) -> &[Edge] {
+        let range = &self.node(node).edges;
+        &self.edges[range.start as usize..range.end as usize]
+    }
+
+    pub(super) fn is_expanded(&self, node: NodeId) -> bool {
+        !self.node(node).edges.is_empty()
+    }
+
+    /// Adds a node for the board of hash s, with the given result. sr is the
+    /// string representation of the board, which is checked against the
+    /// other boards of hash s when checking for collisions.
+    pub(super) fn add_node(
+        &mut self,
+        s: u64,
+        result: Option<GameResult>,
+        sr: impl FnOnce() -> String,
+    ) -> NodeId {
+        if let Some(seen) = &mut self.seen {
+            let sr = sr();
+            match seen.get(&s) {
+                Some(other) if *other != sr => {
+                    panic!("Zobrist hash collision on {s:#018x}: {other} and {sr}")
+                }
+                Some(_) => {}
+                None => {
+                    seen.insert(s, sr);
+                }
+            }
+        }
+        self.nodes.push(Node {
+            s,
+            result,
+            visits: AtomicU32::new(0),
+            edges: 0..0,
+        });
+        (self.nodes.len() - 1) as NodeId
+    }
+
+    /// Returns the child of edge, adding it as in add_node if the edge was
+    /// never taken.
+    pub(super) fn add_child(
+        &mut self,
+        edge: EdgeId,
+        s: u64,
+        result: Option<GameResult>,
+        sr: impl FnOnce() -> String,
+    ) -> NodeId {
+        if let Some(child) = self.edge(edge).child() {
+            // another thread added it first
+            return child;
+        }
+        let child = self.add_node(s, result, sr);
+        self.edges[edge as usize].child = child;
+        child
+    }
+
+    /// Adds the edges of the valid moves of node, with their priors, unless
+    /// the node has them already.
+    pub(super) fn expand(&mut self, node: NodeId, priors: &[f32], valids: &[u8]) {
+        if self.is_expanded(node) {
+            return;
+        }
+        let start = self.edges.len() as EdgeId;
+        for (a, _) in valids.iter().enumerate().filter(|(_, v)| **v == 1) {
+            self.edges.push(Edge {
+                action: a as u16,
+                prior: priors[a],
+                visits: AtomicU32::new(0),
+                value_sum: AtomicU32::new(0),
+                pending: AtomicU32::new(0),
+                child: NONE,
+            });
+        }
+        self.nodes[node as usize].edges = start..self.edges.len() as EdgeId;
+    }
+
+    /// Replaces the priors of the edges of node.
+    pub(super) fn set_priors(&mut self, node: NodeId, priors: &[f32]) {
+        let range = self.node(node).edges.clone();
+        for (edge, prior) in self.edges[range.start as usize..range.end as usize]
+            .iter_mut()
+            .zip(priors)
+        {
+            edge.prior = *prior;
+        }
+    }
+
+    /// Returns the edge of node with the highest upper confidence bound. Every
+    /// pending visit counts as one visit that lost virtual_loss: an edge of n
+    /// visits, p of them pending, and value sum w is scored as n + p visits
+    /// of value sum w - p * virtual_loss.
+    pub(super) fn select(&self, node: NodeId, cpuct: f32, virtual_loss: f32) -> EdgeId {
+        let edges = self.edges(node);
+        let pending: u32 = edges.iter().map(|e| e.pending()).sum();
+        let ns = (self.node(node).visits() + pending) as f32;
+        let sqrt_ns = f32::sqrt(ns);
+        let eps = 1e-8;
+        let mut cur_best = f32::NEG_INFINITY;
+        let mut best_act = 0;
+
+        // pick the action with the highest upper confidence bound
+        for (i, edge) in edges.iter().enumerate() {
+            let pending = edge.pending() as f32;
+            let n = edge.visits() as f32 + pending;
+            let u = if n > 0. {
+                let w = edge.value_sum() - pending * virtual_loss;
+                w / n + cpuct * edge.prior * sqrt_ns / (1.0 + n)
+            } else {
+                cpuct * edge.prior * f32::sqrt(ns + eps)
+            };
+            if u > cur_best {
+                cur_best = u;
+                best_act = i;
+            }
+        }
+        self.node(node).edges.start + best_act as EdgeId
+    }
+
+    pub(super) fn add_pending(&self, edge: EdgeId) {
+        self.edge(edge).pending.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts a visit along path, a list of (node, edge taken), worth v for
+    /// the player to move at its last node and -v one level up. With
+    /// pending, the visit was pending on every edge.
+    pub(super) fn backup(&self, path: &[(NodeId, EdgeId)], mut v: f32, pending: bool) {
+        for (node, edge) in path.iter().rev() {
+            let e = self.edge(*edge);
+            if pending {
+                e.pending.fetch_sub(1, Ordering::Relaxed);
+            }
+            e.value_sum
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                    Some((f32::from_bits(bits) + v).to_bits())
+                })
+                .unwrap();
+            e.visits.fetch_add(1, Ordering::Relaxed);
+            self.node(*node).visits.fetch_add(1, Ordering::Relaxed);
+            v = -v;
+        }
+    }
+
+    /// Makes the shallowest node of hash s the root, keeping its subtree and
+    /// freeing the rest of the tree.
+    ///
+    /// Returns:
+    ///     found: whether the tree has a node of hash s, the tree is left
+    ///            unchanged otherwise
+    pub(super) fn reroot(&mut self, s: u64) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+        let mut queue = VecDeque::from([ROOT]);
+        let root = loop {
+            let Some(node) = queue.pop_front() else {
+                return false;
+            };
+            if self.node(node).s == s {
+                break node;
+            }
+            queue.extend(self.edges(node).iter().filter_map(|e| e.child()));
+        };
+        if root == ROOT {
+            return true;
+        }
+
+        // copy the subtree into new arenas, breadth first
+        let mut tree = Tree {
+            seen: self.seen.take(),
+            ..Default::default()
+        };
+        tree.nodes.push(self.node(root).copy());
+        let mut queue = VecDeque::from([(root, ROOT)]);
+        while let Some((old, new)) = queue.pop_front() {
+            let start = tree.edges.len() as EdgeId;
+            for edge in self.edges(old) {
+                let child = match edge.child() {
+                    Some(child) => {
+                        tree.nodes.push(self.node(child).copy());
+                        let id = (tree.nodes.len() - 1) as NodeId;
+                        queue.push_back((child, id));
+                        id
+                    }
+                    None => NONE,
+                };
+                tree.edges.push(edge.copy(child));
+            }
+            tree.nodes[new as usize].edges = start..tree.edges.len() as EdgeId;
+        }
+        if let Some(seen) = &mut tree.seen {
+            let kept: HashSet<u64> = tree.nodes.iter().map(|n| n.s).collect();
+            seen.retain(|s, _| kept.contains(s));
+        }
+        *self = tree;
+        true
+    }
+
+    /// Frees the whole tree.
+    pub(super) fn clear(&mut self) {
+        let seen = self.seen.take().map(|mut seen| {
+            seen.clear();
+            seen
+        });
+        *self = Tree {
+            seen,
+            ..Default::default()
+        };
+    }
+
+    pub(super) fn memory(&self) -> TreeMemory {
+        TreeMemory {
+            nodes: self.nodes.len(),
+            edges: self.edges.len(),
+            bytes: self.nodes.capacity() * mem::size_of::<Node>()
+                + self.edges.capacity() * mem::size_of::<Edge>(),
+        }
+    }
+}
@@ -0,0 +1,272 @@
+use crate::{
+    board_math::BoardMath,
+    game::{Game, GameResult},
+    notation,
+    zobrist::Zobrist,
+};
+
+/// Directions of the lines on a board: a row, a column and both diagonals.
+const DIRECTIONS: [(isize, isize); 4] = [(0, 1), (1, 0), (1, 1), (1, -1)];
+
+/// Returns whether color has k discs in a line on board.
+pub fn has_line(board: &[Vec<i8>], color: i8, k: usize) -> bool {
+    let rows = board.len() as isize;
+    for x in 0..rows {
+        let cols = board[x as usize].len() as isize;
+        for y in 0..cols {
+            for (dx, dy) in DIRECTIONS {
+                let line = (0..k as isize).all(|i| {
+                    let (i, j) = (x + i * dx, y + i * dy);
+                    i >= 0
+                        && j >= 0
+                        && i < rows
+                        && j < cols
+                        && board[i as usize][j as usize] == color
+                });
+                if line {
+                    return true;
+                }
+            }
+        }
+    }
+    false
+}
+
+/// The m,n,k-game: players take turns placing a disc on any empty square of
+/// a board of m rows and n columns. The first player with k or more discs in
+/// a row, column or diagonal wins, and a full board is a draw. Tic-tac-toe is
+/// the 3,3,3-game and gomoku the 15,15,5-game.
+///
+/// Actions are the squares, numbered row by row, and there is no pass.
+#[derive(Clone)]
+pub struct MnkGame {
+    rows: usize,
+    cols: usize,
+    k: usize,
+    init_board: Vec<Vec<i8>>,
+    bm: BoardMath,
+    zobrist: Zobrist,
+}
+
+impl MnkGame {
+    const SQUARE_CONTENT: [(i8, &'static str); 3] = [(-1, "O"), (0, "-"), (1, "X")];
+
+    /// The m,n,k-game on a board of rows x cols squares with k in a row to
+    /// win.
+    pub fn new_mnk(rows: usize, cols: usize, k: usize) -> Self {
+        assert!(
+            k >= 1 && k <= rows.max(cols),
+            "k = {k} does not fit on a {rows}x{cols} board"
+        );
+        MnkGame {
+            rows,
+            cols,
+            k,
+            init_board: vec![vec![0; cols]; rows],
+            bm: BoardMath::new(rows, cols),
+            zobrist: Zobrist::new(rows * cols),
+        }
+    }
+
+    /// Parses "gomoku", "tictactoe" or "mnk:<m>:<n>:<k>".
+    pub fn from_arg(arg: &str) -> Option<Self> {
+        match arg {
+            "gomoku" => return Some(Self::new(15)),
+            "tictactoe" => return Some(Self::new(3)),
+            _ => {}
+        }
+        let mut params = arg.strip_prefix("mnk:")?.split(':');
+        let mut next = || params.next()?.parse::<usize>().ok();
+        let (rows, cols, k) = (next()?, next()?, next()?);
+        if next().is_some() || k == 0 || k > rows.max(cols) {
+            return None;
+        }
+        Some(Self::new_mnk(rows, cols, k))
+    }
+
+    fn square_content(color: i8) -> &'static str {
+        let mut content = "";
+        for tup in Self::SQUARE_CONTENT {
+            if tup.0 == color {
+                content = tup.1;
+            }
+        }
+        content
+    }
+}
+
+impl Game for MnkGame {
+    type State = Vec<Vec<i8>>;
+    /// The square x * cols + y.
+    type Action = usize;
+    /// The square the disc was placed on.
+    type Undo = usize;
+
+    /// An n x n board with n in a row to win, but no more than five, so
+    /// new(3) is tic-tac-toe and new(15) is gomoku.
+    fn new(n: usize) -> Self {
+        Self::new_mnk(n, n, n.min(5))
+    }
+
+    fn get_init_board(&self) -> &Vec<Vec<i8>> {
+        &self.init_board
+    }
+
+    fn get_board_size(&self) -> (usize, usize) {
+        (self.rows, self.cols)
+    }
+
+    fn get_state_size(&self, board: &Self::State) -> (usize, usize) {
+        (board.len(), board.first().map_or(0, |row| row.len()))
+    }
+
+    fn get_action_size(&self) -> usize {
+        self.rows * self.cols
+    }
+
+    fn action_to_index(&self, action: usize) -> usize {
+        action
+    }
+
+    fn index_to_action(&self, index: usize) -> usize {
+        index
+    }
+
+    fn get_next_state(
+        &self,
+        board: &Vec<Vec<i8>>,
+        player: i8,
+        action: usize,
+    ) -> (Vec<Vec<i8>>, i8) {
+        let mut next_board = board.clone();
+        let (next_player, _) = self.apply(&mut next_board, player, action);
+        (next_board, next_player)
+    }
+
+    fn apply(&self, board: &mut Vec<Vec<i8>>, player: i8, action: usize) -> (i8, usize) {
+        let (x, y) = (action / self.cols, action % self.cols);
+        assert_eq!(board[x][y], 0, "the square should be empty");
+        board[x][y] = player;
+        (-player, action)
+    }
+
+    fn undo(&self, board: &mut Vec<Vec<i8>>, undo: &usize) {
+        board[undo / self.cols][undo % self.cols] = 0;
+    }
+
+    fn get_valid_moves(&self, board: &Vec<Vec<i8>>, _player: i8) -> Vec<u8> {
+        board
+            .iter()
+            .flatten()
+            .map(|square| (*square == 0) as u8)
+            .collect()
+    }
+
+    /// return None if not ended, otherwise the result for player: a win or a
+    /// loss by a margin of 1 when either player has k in a row, or a draw
+    /// when the board is full
+    fn get_game_ended(&self, board: &Vec<Vec<i8>>, player: i8) -> Option<GameResult> {
+        if has_line(board, player, self.k) {
+            return Some(GameResult::from_margin(1));
+        }
+        if has_line(board, -player, self.k) {
+            return Some(GameResult::from_margin(-1));
+        }
+        if board.iter().flatten().all(|square| *square != 0) {
+            return Some(GameResult::from_margin(0));
+        }
+        None
+    }
+
+    fn get_canonical_form(&self, board: &Vec<Vec<i8>>, player: i8) -> Vec<Vec<i8>> {
+        board
+            .iter()
+            .map(|row| row.iter().map(|square| square * player).collect())
+            .collect()
+    }
+
+    /// Returns the board under every symmetry of BoardMath: eight for a
+    /// square board and four for a rectangular one.
+    fn get_symmetries(&self, board: &Vec<Vec<i8>>, pi: &[f32]) -> Vec<(Vec<Vec<i8>>, Vec<f32>)> {
+        let mut symmetries = vec![(board.clone(), pi.to_vec())];
+        for (t_2d, t_1d) in self.bm.symmetries() {
+            symmetries.push((
+                BoardMath::apply_2d(board, t_2d),
+                BoardMath::apply_1d(pi, t_1d),
+            ));
+        }
+        symmetries
+    }
+
+    fn string_representation(&self, board: &Vec<Vec<i8>>) -> String {
+        let mut sr = String::with_capacity(self.rows * self.cols);
+        for square in board.iter().flatten() {
+            sr.push_str(Self::square_content(*square));
+        }
+        sr
+    }
+
+    fn display(&self, board: &Vec<Vec<i8>>) {
+        print!("    ");
+        for y in 0..self.cols {
+            print!("{} ", (b'a' + y as u8) as char);
+        }
+        println!();
+        for (x, row) in board.iter().enumerate() {
+            print!("{:>2}| ", x + 1);
+            for square in row {
+                print!("{} ", Self::square_content(*square));
+            }
+            println!("|");
+        }
+    }
+
+    /// One plane with 1 for the discs of player 1, -1 for those of player -1
+    /// and 0 for empty squares.
+    fn encode(&self, board: &Vec<Vec<i8>>) -> Vec<f32> {
+        board
+            .iter()
+            .flatten()
+            .map(|square| *square as f32)
+            .collect()
+    }
+
+    fn action_to_string(&self, action: usize) -> String {
+        notation::action_to_string(action, self.rows, self.cols)
+    }
+
+    /// Squares are written as in Othello, and "pass" is not a move.
+    fn string_to_action(&self, s: &str) -> Option<usize> {
+        notation::string_to_action(s, self.rows, self.cols).filter(|a| *a < self.rows * self.cols)
+    }
+
+    fn zobrist_hash(&self, board: &Vec<Vec<i8>>) -> u64 {
+        board
+            .iter()
+            .flatten()
+            .enumerate()
+            .filter(|(_, square)| **square != 0)
+            .fold(0, |hash, (i, square)| {
+                hash ^ self.zobrist.hash_square(i, *square)
+            })
+    }
+
+    fn get_next_state_hashed(
+        &self,
+        board: &Vec<Vec<i8>>,
+        player: i8,
+        action: usize,
+        hash: u64,
+    ) -> (Vec<Vec<i8>>, i8, u64) {
+        let (next_board, next_player) = self.get_next_state(board, player, action);
+        let hash = hash ^ self.zobrist.hash_square(action, player);
+        (next_board, next_player, hash)
+    }
+
+    fn get_canonical_hash(&self, hash: u64, player: i8) -> u64 {
+        Zobrist::canonical(hash, player)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,186 @@
+use rand::Rng;
+
+use super::{has_line, MnkGame};
+use crate::game::{conformance, Game, GameResult};
+
+/// Plays the squares in order from the initial board, starting with player 1.
+fn play(game: &MnkGame, squares: &[usize]) -> (Vec<Vec<i8>>, i8) {
+    let mut board = game.get_init_board().clone();
+    let mut player = 1;
+    for square in squares {
+        (board, player) = game.get_next_state(&board, player, *square);
+    }
+    (board, player)
+}
+
+#[test]
+fn sizes() {
+    let tictactoe = MnkGame::new(3);
+    assert_eq!(tictactoe.get_board_size(), (3, 3));
+    // no pass action
+    assert_eq!(tictactoe.get_action_size(), 9);
+    assert_eq!(tictactoe.k, 3);
+    let gomoku = MnkGame::new(15);
+    assert_eq!(gomoku.get_action_size(), 225);
+    assert_eq!(gomoku.k, 5);
+    let game = MnkGame::new_mnk(4, 6, 4);
+    assert_eq!(game.get_board_size(), (4, 6));
+    assert_eq!(game.get_action_size(), 24);
+}
+
+#[test]
+fn from_arg() {
+    assert_eq!(MnkGame::from_arg("gomoku").unwrap().get_action_size(), 225);
+    assert_eq!(MnkGame::from_arg("tictactoe").unwrap().get_action_size(), 9);
+    let game = MnkGame::from_arg("mnk:7:9:4").unwrap();
+    assert_eq!((game.rows, game.cols, game.k), (7, 9, 4));
+    for arg in [
+        "mnk:7:9",
+        "mnk:7:9:4:1",
+        "mnk:3:3:4",
+        "mnk:3:3:0",
+        "mnk:a:3:3",
+        "othello",
+    ] {
+        assert!(MnkGame::from_arg(arg).is_none(), "{arg}");
+    }
+}
+
+#[test]
+fn valid_moves_are_empty_squares() {
+    let game = MnkGame::new(3);
+    let (board, player) = play(&game, &[4, 0]);
+    assert_eq!(player, 1);
+    assert_eq!(
+        game.get_valid_moves(&board, player),
+        vec![0, 1, 1, 1, 0, 1, 1, 1, 1]
+    );
+}
+
+#[test]
+fn wins() {
+    let game = MnkGame::new(3);
+    let row = [0, 3, 1, 4, 2];
+    let column = [0, 1, 3, 4, 6];
+    let diagonal = [0, 1, 4, 2, 8];
+    let anti_diagonal = [2, 0, 4, 1, 6];
+    for squares in [row, column, diagonal, anti_diagonal] {
+        let (board, player) = play(&game, &squares[..4]);
+        assert_eq!(game.get_game_ended(&board, player), None, "{squares:?}");
+        let (board, player) = play(&game, &squares);
+        assert_eq!(
+            game.get_game_ended(&board, player),
+            Some(GameResult::from_margin(-1)),
+            "{squares:?}"
+        );
+        assert_eq!(
+            game.get_game_ended(&board, -player),
+            Some(GameResult::from_margin(1))
+        );
+    }
+}
+
+#[test]
+fn longer_lines_win() {
+    let board: Vec<Vec<i8>> = vec![vec![0, 1, 1, 1, 1, 1, 1]];
+    assert!(has_line(&board, 1, 5));
+    assert!(!has_line(&board, 1, 7));
+    assert!(!has_line(&board, -1, 1));
+}
+
+#[test]
+fn full_board_is_a_draw() {
+    let game = MnkGame::new(3);
+    // X O X / X O O / O X X
+    let (board, player) = play(&game, &[0, 1, 2, 4, 3, 5, 7, 6, 8]);
+    assert_eq!(
+        game.get_game_ended(&board, player),
+        Some(GameResult::from_margin(0))
+    );
+    assert_eq!(game.get_valid_moves(&board, player), vec![0; 9]);
+}
+
+#[test]
+fn symmetries_square_and_rectangular() {
+    for (game, count) in [(MnkGame::new(3), 8), (MnkGame::new_mnk(3, 4, 3), 4)] {
+        let (board, _) = play(&game, &[0, 1, 5]);
+        let pi: Vec<f32> = (0..game.get_action_size()).map(|a| a as f32).collect();
+        let symmetries = game.get_symmetries(&board, &pi);
+        assert_eq!(symmetries.len(), count);
+        for (sym_board, sym_pi) in &symmetries {
+            // every disc ends up on the square its policy entry moved to
+            let squares = game.encode(&board);
+            let sym_squares = game.encode(sym_board);
+            for a in 0..pi.len() {
+                assert_eq!(sym_squares[a], squares[sym_pi[a] as usize]);
+            }
+        }
+        let mut representations: Vec<String> = symmetries
+            .iter()
+            .map(|(b, _)| game.string_representation(b))
+            .collect();
+        representations.sort();
+        representations.dedup();
+        assert_eq!(representations.len(), count);
+    }
+}
+
+#[test]
+fn apply_undo_and_hashes() {
+    let mut rng = rand::thread_rng();
+    for game in [MnkGame::new(3), MnkGame::new(9), MnkGame::new_mnk(4, 7, 4)] {
+        for _ in 0..20 {
+            let mut board = game.get_init_board().clone();
+            let mut player = 1;
+            let mut hash = game.zobrist_hash(&board);
+            while game.get_game_ended(&board, player).is_none() {
+                let valids = game.get_valid_moves(&board, player);
+                let legal: Vec<usize> = (0..valids.len()).filter(|a| valids[*a] == 1).collect();
+                let action = legal[rng.gen_range(0..legal.len())];
+                let (next_board, next_player, next_hash) =
+                    game.get_next_state_hashed(&board, player, action, hash);
+                assert_eq!(next_hash, game.zobrist_hash(&next_board));
+                assert_eq!(
+                    game.get_canonical_hash(next_hash, next_player),
+                    game.zobrist_hash(&game.get_canonical_form(&next_board, next_player))
+                );
+
+                let mut applied = board.clone();
+                let (applied_player, undo) = game.apply(&mut applied, player, action);
+                assert_eq!((&applied, applied_player), (&next_board, next_player));
+                game.undo(&mut applied, &undo);
+                assert_eq!(applied, board);
+
+                (board, player, hash) = (next_board, next_player, next_hash);
+            }
+        }
+    }
+}
+
+#[test]
+fn zobrist_beyond_64_squares() {
+    let game = MnkGame::new(15);
+    let (a, _) = play(&game, &[0, 224]);
+    let (b, _) = play(&game, &[0, 223]);
+    assert_ne!(game.zobrist_hash(&a), game.zobrist_hash(&b));
+}
+
+#[test]
+fn action_strings() {
+    let game = MnkGame::new(15);
+    for action in 0..225 {
+        let s = game.action_to_string(action);
+        assert_eq!(game.string_to_action(&s), Some(action));
+    }
+    assert_eq!(game.action_to_string(224), "o15");
+    for s in ["pass", "pa", "p1", "a16"] {
+        assert_eq!(game.string_to_action(s), None, "{s}");
+    }
+}
+
+#[test]
+fn conformance() {
+    for game in [MnkGame::new(3), MnkGame::new_mnk(3, 4, 3), MnkGame::new(7)] {
+        conformance::check(&game, 20);
+    }
+}
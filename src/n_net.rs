@@ -1,14 +1,15 @@
-use std::{fs, marker::PhantomData};
+use std::fs;
 
 use crate::{
-    game::Game,
+    game::{Example, Game},
     neural_net::NeuralNet,
     othello_neural_net::{Model, ModelConfig},
+    tensor,
 };
 use burn::{
-    module::Module,
+    module::{AutodiffModule, Module},
     optim::{AdamConfig, GradientsParams},
-    tensor::{backend::AutodiffBackend, Data, Float, Shape, Tensor},
+    tensor::{backend::AutodiffBackend, Tensor},
 };
 use burn::{
     optim::Optimizer,
@@ -26,30 +27,17 @@ pub struct NNetWrapper<B: AutodiffBackend, G: Game> {
     // num_channels: i32,
     device: B::Device,
     nnet: Model<B>,
-    phantom: PhantomData<G>,
+    game: G,
 }
 
 impl<G: Game, B: AutodiffBackend> NeuralNet<B, G> for NNetWrapper<B, G> {
     fn new(game: G, device: B::Device) -> NNetWrapper<B, G> {
-        let _board_x = game.get_board_size().0;
-        let _board_y = game.get_board_size().1;
-        let _action_size = game.get_action_size();
-        let mc = ModelConfig::new();
-        let nnet = mc.init::<B>(&device);
-        NNetWrapper {
-            lr: 0.001,
-            // dropout: 0.3,
-            epochs: 10,
-            batch_size: 64,
-            // cuda: true,
-            // num_channels: 512,
-            device,
-            nnet,
-            phantom: PhantomData,
-        }
+        let (board_x, board_y) = game.get_board_size();
+        let mc = ModelConfig::new(board_x, board_y, game.get_action_size());
+        Self::with_config(game, device, &mc)
     }
 
-    fn train(&self, examples: &Vec<(Vec<Vec<i8>>, Vec<f32>, i8)>) {
+    fn train(&self, examples: &[Example<G>]) {
         let mut optimizer = AdamConfig::new().init();
 
         for epoch in 0..self.epochs {
@@ -67,39 +55,15 @@ impl<G: Game, B: AutodiffBackend> NeuralNet<B, G> for NNetWrapper<B, G> {
                 // let sample_ids: usize =
                 //     rand::thread_rng().gen_range(examples.len()..self.batch_size as usize);
 
-                let mut boards_array: [[[f64; 6]; 6]; 64] = [[[1.0; 6]; 6]; 64];
-                let mut pis_vec = Vec::<f32>::with_capacity(64 * 6 * 6);
-                let mut vs_vec: Vec<f32> = Vec::<f32>::with_capacity(64);
-                for i in 0..sample_ids.len() {
-                    let example = examples.get(sample_ids[i]).unwrap();
-                    let board = example.clone().0;
-                    for j in 0..board.len() {
-                        for k in 0..board.len() {
-                            boards_array[i][j][k] =
-                                f64::from(board.get(j).unwrap().get(k).unwrap().to_owned());
-                        }
-                    }
-
-                    let mut pi: Vec<f32> = example.1.clone();
-                    pis_vec.append(&mut pi);
-
-                    let v: i8 = example.2;
-                    vs_vec.push(v as f32);
-                    // vs_vec.append(&mut v); can be used later to make 2 dimensional [64, 1]
-                }
-
-                let boards_data: Data<_, 3> = Data::<f64, 3>::from(boards_array).convert();
-                let mut boards_tensor: Tensor<B, 3> =
-                    Tensor::<B, 3>::from_data(boards_data, &self.device);
-                boards_tensor = boards_tensor.require_grad();
-
-                let pis_shape = Shape::new([64, 37]);
-                let pis_data: Data<_, 2> = Data::<f32, 2>::new(pis_vec, pis_shape).convert();
-                let target_pis: Tensor<B, 2> = Tensor::<B, 2>::from_data(pis_data, &self.device);
-
-                let vs_shape = Shape::new([64]);
-                let vs_data: Data<_, 1> = Data::<f32, 1>::new(vs_vec, vs_shape).convert();
-                let target_vs: Tensor<B, 1> = Tensor::<B, 1>::from_data(vs_data, &self.device);
+                let batch: Vec<&Example<G>> = sample_ids.iter().map(|i| &examples[*i]).collect();
+                let boards: Vec<&G::State> = batch.iter().map(|e| &e.0).collect();
+                let pis: Vec<&[f32]> = batch.iter().map(|e| &e.1[..]).collect();
+                let vs: Vec<f32> = batch.iter().map(|e| e.2).collect();
+
+                let boards_tensor =
+                    tensor::boards::<B, G>(&self.game, &boards, &self.device).require_grad();
+                let target_pis = tensor::policies::<B>(&pis, &self.device);
+                let target_vs = tensor::values::<B>(&vs, &self.device);
 
                 // compute output
                 let output = &self.nnet.forward(boards_tensor);
@@ -115,31 +79,36 @@ impl<G: Game, B: AutodiffBackend> NeuralNet<B, G> for NNetWrapper<B, G> {
         }
     }
 
-    /// board: np array with board
-    fn predict(&self, board: &Vec<Vec<i8>>) -> (Vec<f32>, f32) {
+    /// board: canonical board
+    fn predict(&self, board: &G::State) -> (Vec<f32>, f32) {
         // timing
         // start = time.time()
 
-        let mut floats: Vec<f32> = Vec::with_capacity(36);
-        for inner in board {
-            for i in inner {
-                floats.push(i.clone() as f32);
-            }
-        }
-        let shape = Shape::new([1, 6, 6]);
-        let data = Data::<f32, 3>::new(floats, shape).convert();
-        let b: Tensor<B, 3, Float> = Tensor::<B, 3>::from_data(data, &self.device);
-        let model = self.nnet.clone();
-        let model = model.no_grad();
-        let output: (Tensor<B, 2>, Tensor<B, 2>) = model.forward(b);
-
-        let pi = output.0.exp().to_data().convert::<f32>().value;
-        let v = output.1.to_data().convert::<f32>().value[0];
+        let (pi, v) = self.predict_batch(&[board]).pop().unwrap();
 
         // print('PREDICTION TIME TAKEN : {0:03f}'.format(time.time()-start))
         return (pi, v);
     }
 
+    fn predict_batch(&self, boards: &[&G::State]) -> Vec<(Vec<f32>, f32)> {
+        if boards.is_empty() {
+            // every descent of a batched search may end on a terminal board
+            return Vec::new();
+        }
+        // the inference model, whose batch norm uses its running statistics,
+        // so that the boards of a batch do not change each other's prediction
+        let model = self.nnet.valid();
+        let b = tensor::boards::<B::InnerBackend, G>(&self.game, boards, &self.device);
+        let output: (Tensor<B::InnerBackend, 2>, Tensor<B::InnerBackend, 2>) = model.forward(b);
+
+        let pis = tensor::to_vec(output.0.exp());
+        let vs = tensor::to_vec(output.1);
+        pis.chunks(self.game.get_action_size())
+            .map(|pi| pi.to_vec())
+            .zip(vs)
+            .collect()
+    }
+
     fn save_checkpoint(&self, folder: &str, filename: &str) {
         let file_path = format!("{folder}/{filename}");
         fs::create_dir_all(folder).expect("Should be able to create path");
@@ -164,6 +133,23 @@ impl<G: Game, B: AutodiffBackend> NeuralNet<B, G> for NNetWrapper<B, G> {
 }
 
 impl<B: AutodiffBackend, G: Game> NNetWrapper<B, G> {
+    /// Returns an untrained network of the model that config describes, such
+    /// as one with fewer channels than new gives.
+    pub fn with_config(game: G, device: B::Device, config: &ModelConfig) -> Self {
+        let nnet = config.init::<B>(&device);
+        NNetWrapper {
+            lr: 0.001,
+            // dropout: 0.3,
+            epochs: 10,
+            batch_size: 64,
+            // cuda: true,
+            // num_channels: 512,
+            device,
+            nnet,
+            game,
+        }
+    }
+
     fn loss_pi(&self, targets: &Tensor<B, 2>, outputs: Tensor<B, 2>) -> Tensor<B, 1> {
         let product = targets.clone().mul(outputs);
         let sum = -product.sum();
@@ -1,6 +1,6 @@
 use burn::tensor::backend::AutodiffBackend;
 
-use crate::game::Game;
+use crate::game::{Example, Game};
 
 pub trait NeuralNet<B: AutodiffBackend, G: Game> {
     /// This class specifies the base NeuralNet class. To define your own neural
@@ -20,7 +20,7 @@ pub trait NeuralNet<B: AutodiffBackend, G: Game> {
     ///               (board, pi, v). pi is the MCTS informed policy vector for
     ///               the given board, and v is its value. The examples has
     ///               board in its canonical form.
-    fn train(&self, examples: &Vec<(Vec<Vec<i8>>, Vec<f32>, i8)>);
+    fn train(&self, examples: &[Example<G>]);
 
     /// Input:
     /// board: current board in its canonical form.
@@ -29,7 +29,16 @@ pub trait NeuralNet<B: AutodiffBackend, G: Game> {
     ///     pi: a policy vector for the current board- a numpy array of length
     ///         game.getActionSize
     ///     v: a float in [-1,1] that gives the value of the current board
-    fn predict(&self, board: &Vec<Vec<i8>>) -> (Vec<f32>, f32);
+    fn predict(&self, board: &G::State) -> (Vec<f32>, f32);
+
+    /// Input:
+    /// boards: boards in their canonical form, evaluated together.
+    ///
+    /// Returns:
+    ///     predictions: (pi, v) of every board, as predict returns them
+    fn predict_batch(&self, boards: &[&G::State]) -> Vec<(Vec<f32>, f32)> {
+        boards.iter().map(|board| self.predict(board)).collect()
+    }
 
     /// Saves the current neural network (with its parameters) in
     /// folder/filename
@@ -0,0 +1,157 @@
+use std::fmt;
+
+use crate::{game::Game, othello::Othello};
+
+/// Standard board coordinates: a column letter followed by a row number, so
+/// "a1" is square (0, 0) and "c2" is square (1, 2). The action after the last
+/// square is the pass action and is written "pass".
+pub const PASS: &str = "pass";
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum NotationError {
+    /// The text is not a coordinate on the board.
+    InvalidMove(String),
+    /// The move is not valid in the position it was played in.
+    IllegalMove { ply: usize, action: String },
+}
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NotationError::InvalidMove(m) => write!(f, "\"{m}\" is not a move"),
+            NotationError::IllegalMove { ply, action } => {
+                write!(f, "{action} at ply {ply} is not a legal move")
+            }
+        }
+    }
+}
+
+/// Returns the coordinate of action on a board with rows x cols squares.
+pub fn action_to_string(action: usize, rows: usize, cols: usize) -> String {
+    if action == rows * cols {
+        return PASS.to_owned();
+    }
+    let col = (b'a' + (action % cols) as u8) as char;
+    format!("{col}{}", action / cols + 1)
+}
+
+/// Parses a coordinate, or "pass", on a board with rows x cols squares.
+/// Upper case letters and "PA" (used by many transcripts) are accepted too.
+pub fn string_to_action(s: &str, rows: usize, cols: usize) -> Option<usize> {
+    let s = s.trim().to_ascii_lowercase();
+    if s == PASS || s == "pa" {
+        return Some(rows * cols);
+    }
+    let mut chars = s.chars();
+    let col = chars.next()?;
+    if !col.is_ascii_lowercase() {
+        return None;
+    }
+    let col = (col as u8 - b'a') as usize;
+    let row = chars.as_str().parse::<usize>().ok()?;
+    if col >= cols || row == 0 || row > rows {
+        return None;
+    }
+    Some((row - 1) * cols + col)
+}
+
+/// Splits a transcript such as "f5d6c3d3c4" or "f5 d6 pass c3" into moves.
+pub fn split_transcript(transcript: &str) -> Result<Vec<&str>, NotationError> {
+    let mut moves = Vec::new();
+    let mut rest = transcript.trim_start();
+    while !rest.is_empty() {
+        let lower = rest.to_ascii_lowercase();
+        let len = if lower.starts_with(PASS) {
+            PASS.len()
+        } else if lower.starts_with("pa") {
+            2
+        } else if lower.starts_with(|c: char| c.is_ascii_lowercase()) {
+            1 + rest[1..]
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len() - 1)
+        } else {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            return Err(NotationError::InvalidMove(rest[..end].to_owned()));
+        };
+        moves.push(&rest[..len]);
+        rest = rest[len..].trim_start();
+    }
+    Ok(moves)
+}
+
+/// Writes the actions of a game as a transcript, leaving out passes as is
+/// customary.
+pub fn transcript<G: Game>(game: &G, actions: &[G::Action]) -> String {
+    actions
+        .iter()
+        .map(|a| game.action_to_string(*a))
+        .filter(|m| m != PASS)
+        .collect()
+}
+
+/// Replays a transcript from board with player to move, checking every move
+/// for legality. Passes may be written out or left implicit in games that
+/// have them.
+///
+/// Returns:
+///     positions: every (board, player to move) of the game, starting with the
+///                given board
+pub fn replay<G: Game>(
+    game: &G,
+    mut board: G::State,
+    mut player: i8,
+    transcript: &str,
+) -> Result<Vec<(G::State, i8)>, NotationError> {
+    let pass = game.string_to_action(PASS);
+    let mut positions = vec![(board.clone(), player)];
+    for m in split_transcript(transcript)? {
+        let action = game
+            .string_to_action(m)
+            .ok_or_else(|| NotationError::InvalidMove(m.to_owned()))?;
+        if let Some(pass) = pass {
+            let valids = game.get_valid_moves(&board, player);
+            if action != pass
+                && valids[game.action_to_index(pass)] == 1
+                && game.get_game_ended(&board, player).is_none()
+            {
+                // implicit pass
+                (board, player) = game.get_next_state(&board, player, pass);
+                positions.push((board.clone(), player));
+            }
+        }
+        (board, player) = game.try_next_state(&board, player, action).map_err(|_| {
+            NotationError::IllegalMove {
+                ply: positions.len(),
+                action: game.action_to_string(action),
+            }
+        })?;
+        positions.push((board.clone(), player));
+    }
+    Ok(positions)
+}
+
+/// Runs `othello replay <transcript> [board size | position]`, displaying every
+/// position of the game.
+pub fn command(args: &[String]) {
+    let t = args
+        .first()
+        .expect("Usage: othello replay <transcript> [board size | position]");
+    let (game, board, player) =
+        match Othello::from_command_arg(args.get(1).map_or("8", |v| v.as_str())) {
+            Ok(position) => position,
+            Err(e) => return println!("Invalid position: {e}"),
+        };
+
+    match replay(&game, board, player, t) {
+        Ok(positions) => {
+            for (ply, (board, player)) in positions.iter().enumerate() {
+                println!("Ply {ply}: {}", game.to_position_str(board, *player));
+                game.display(board);
+            }
+        }
+        Err(e) => println!("Invalid transcript: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,105 @@
+use rand::Rng;
+
+use super::{
+    action_to_string, replay, split_transcript, string_to_action, transcript, NotationError,
+};
+use crate::{game::Game, othello::Othello};
+
+#[test]
+fn coordinates_8() {
+    assert_eq!(action_to_string(0, 8, 8), "a1");
+    assert_eq!(action_to_string(37, 8, 8), "f5");
+    assert_eq!(action_to_string(63, 8, 8), "h8");
+    assert_eq!(action_to_string(64, 8, 8), "pass");
+    for action in 0..65 {
+        let s = action_to_string(action, 8, 8);
+        assert_eq!(string_to_action(&s, 8, 8), Some(action));
+        assert_eq!(string_to_action(&s.to_uppercase(), 8, 8), Some(action));
+    }
+    assert_eq!(string_to_action("PA", 8, 8), Some(64));
+}
+
+#[test]
+fn coordinates_invalid() {
+    for s in ["", "i1", "a0", "a9", "5f", "f", "pas"] {
+        assert_eq!(string_to_action(s, 8, 8), None, "{s}");
+    }
+    assert_eq!(string_to_action("f5", 4, 4), None);
+}
+
+#[test]
+fn split_transcripts() {
+    assert_eq!(
+        split_transcript("f5d6c3 pass D3c4PA").unwrap(),
+        vec!["f5", "d6", "c3", "pass", "D3", "c4", "PA"]
+    );
+    assert_eq!(split_transcript("a10b3").unwrap(), vec!["a10", "b3"]);
+    assert_eq!(
+        split_transcript("f5 5d"),
+        Err(NotationError::InvalidMove("5d".to_owned()))
+    );
+}
+
+#[test]
+fn replay_opening_8() {
+    let othello = Othello::new(8);
+    let positions = replay(&othello, *othello.get_init_board(), 1, "f5d6c3d3c4").unwrap();
+    assert_eq!(positions.len(), 6);
+    let (board, player) = &positions[1];
+    assert_eq!(*player, -1);
+    // f5 flips e5
+    assert_eq!(board.get(4, 5), 1);
+    assert_eq!(board.get(4, 4), 1);
+    let (board, player) = positions.last().unwrap();
+    assert_eq!(*player, -1);
+    assert_eq!(othello.get_valid_moves(board, -1).iter().sum::<u8>(), 6);
+}
+
+#[test]
+fn replay_illegal_move() {
+    let othello = Othello::new(8);
+    assert_eq!(
+        replay(&othello, *othello.get_init_board(), 1, "f5f5"),
+        Err(NotationError::IllegalMove {
+            ply: 2,
+            action: "f5".to_owned()
+        })
+    );
+    assert_eq!(
+        replay(&othello, *othello.get_init_board(), 1, "pass"),
+        Err(NotationError::IllegalMove {
+            ply: 1,
+            action: "pass".to_owned()
+        })
+    );
+    assert_eq!(
+        replay(&othello, *othello.get_init_board(), 1, "f5z9"),
+        Err(NotationError::InvalidMove("z9".to_owned()))
+    );
+}
+
+#[test]
+fn replay_random_games_with_implicit_passes() {
+    let othello = Othello::new(4);
+    let mut rng = rand::thread_rng();
+    for _ in 0..50 {
+        let mut board = *othello.get_init_board();
+        let mut player = 1;
+        let mut actions = Vec::new();
+        let mut positions = vec![(board, player)];
+        while othello.get_game_ended(&board, player).is_none() {
+            let valids = othello.get_valid_moves(&board, player);
+            let legal: Vec<usize> = (0..valids.len()).filter(|a| valids[*a] == 1).collect();
+            let action = othello.index_to_action(legal[rng.gen_range(0..legal.len())]);
+            actions.push(action);
+            (board, player) = othello.get_next_state(&board, player, action);
+            positions.push((board, player));
+        }
+        let t = transcript(&othello, &actions);
+        assert!(!t.contains("pass"));
+        assert_eq!(
+            replay(&othello, *othello.get_init_board(), 1, &t).unwrap(),
+            positions
+        );
+    }
+}
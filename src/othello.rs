@@ -1,163 +1,552 @@
-use crate::{board::Board, board_math::BoardMath, game::Game};
+use std::{collections::HashSet, fmt, str::FromStr};
+
+use rand::seq::SliceRandom;
+
+use crate::{
+    board::{Board, MoveUndo},
+    board_math::BoardMath,
+    game::{Game, GameResult},
+    notation,
+    zobrist::Zobrist,
+};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PositionError {
+    WrongLength {
+        expected: usize,
+        found: usize,
+    },
+    InvalidSquare(char),
+    InvalidSideToMove(char),
+    /// A board that is not rows x cols squares with both even, at least 4,
+    /// and no more than 64 squares in total.
+    InvalidSize {
+        rows: usize,
+        cols: usize,
+    },
+}
+
+impl fmt::Display for PositionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PositionError::WrongLength { expected, found } => write!(
+                f,
+                "expected {expected} squares and the side to move, found {found} characters"
+            ),
+            PositionError::InvalidSquare(c) => write!(f, "'{c}' is not a square"),
+            PositionError::InvalidSideToMove(c) => write!(f, "'{c}' is not a side to move"),
+            PositionError::InvalidSize { rows, cols } => write!(
+                f,
+                "{rows}x{cols} is not a board size: both sides should be even and at least 4, with up to 64 squares"
+            ),
+        }
+    }
+}
+
+/// Which player wins a finished game. Both variants share the same moves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rules {
+    /// The player with more discs wins.
+    Standard,
+    /// Anti-Othello (also called reversed Othello): the player with fewer
+    /// discs wins.
+    Anti,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct UnknownRules(pub String);
+
+impl fmt::Display for UnknownRules {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown rules \"{}\", expected standard or anti", self.0)
+    }
+}
+
+impl FromStr for Rules {
+    type Err = UnknownRules;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "standard" => Ok(Rules::Standard),
+            "anti" => Ok(Rules::Anti),
+            _ => Err(UnknownRules(s.to_owned())),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum OpeningsError {
+    /// The openings argument is not one of the accepted forms.
+    InvalidArg(String),
+    /// The position of a position: argument does not parse.
+    Position(PositionError),
+    /// No opening of the requested kind exists on this board.
+    NoOpenings,
+}
+
+impl fmt::Display for OpeningsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OpeningsError::InvalidArg(arg) => write!(
+                f,
+                "\"{arg}\" is not standard, random:<count>:<plies>, handicap:<corners> or position:<position>"
+            ),
+            OpeningsError::Position(e) => write!(f, "invalid position: {e}"),
+            OpeningsError::NoOpenings => write!(f, "no opening of this kind exists"),
+        }
+    }
+}
+
+/// A move of Othello: a disc placed on square (x, y), or a pass.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Move {
+    Place(usize, usize),
+    Pass,
+}
 
 #[derive(Clone)]
 pub struct Othello {
-    n: usize,
-    b: Board,
+    rows: usize,
+    cols: usize,
+    rules: Rules,
+    init_board: Board,
+    // start boards with player 1 to move, the initial board when empty
+    openings: Vec<Board>,
     bm: BoardMath,
+    zobrist: Zobrist,
 }
 
 impl Othello {
-    const SQUARE_CONTENT: [(i8, &'static str); 3] = [(-1, "X"), (0, "-"), (1, "O")];
-    pub fn display(board: &Vec<Vec<i8>>) {
-        let n = board.len();
-        print!("   ");
-        for y in 0..n {
-            print!("{:?} ", y);
+    /// Player 1 moves first and plays black, which Othello tools write as X.
+    const SQUARE_CONTENT: [(i8, &'static str); 3] = [(-1, "O"), (0, "-"), (1, "X")];
+
+    /// Othello on a board of rows x cols squares, starting from the four
+    /// center discs. Panics unless the size passes check_size.
+    pub fn new_rect(rows: usize, cols: usize) -> Self {
+        if let Err(e) = Self::check_size(rows, cols) {
+            panic!("{e}");
         }
-        println!("");
-        println!("--------------------");
-        for x in 0..n {
-            print!("{:?}| ", x);
-            for y in 0..n {
-                let mut content = "";
-                for tup in Self::SQUARE_CONTENT {
-                    if tup.0 == board[x][y] {
-                        content = tup.1;
-                    }
+        Othello {
+            rows,
+            cols,
+            rules: Rules::Standard,
+            init_board: Board::new(rows, cols),
+            openings: Vec::new(),
+            bm: BoardMath::new(rows, cols),
+            zobrist: Zobrist::new(rows * cols),
+        }
+    }
+
+    /// Returns the same game played with rules.
+    pub fn with_rules(mut self, rules: Rules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    /// Returns the same game started from openings instead of the initial
+    /// board. Every opening is a board with player 1 to move.
+    pub fn with_openings(mut self, openings: Vec<Board>) -> Self {
+        self.openings = openings;
+        self
+    }
+
+    /// Reads the openings argument of the configuration:
+    ///
+    ///     standard                   the initial board
+    ///     random:<count>:<plies>     XOT-style openings, see random_openings
+    ///     handicap:<corners>         see handicap_board
+    ///     position:<position>        a position string, see parse_position
+    ///
+    /// Returns:
+    ///     openings: the start boards, empty for standard
+    pub fn openings_from_arg(&self, arg: &str) -> Result<Vec<Board>, OpeningsError> {
+        let invalid = || OpeningsError::InvalidArg(arg.to_owned());
+        let (kind, rest) = arg.split_once(':').unwrap_or((arg, ""));
+        let openings = match kind {
+            "standard" if rest.is_empty() => return Ok(Vec::new()),
+            "random" => {
+                let (count, plies) = rest.split_once(':').ok_or_else(invalid)?;
+                let count = count.parse::<usize>().map_err(|_| invalid())?;
+                let plies = plies.parse::<usize>().map_err(|_| invalid())?;
+                self.random_openings(count, plies)
+            }
+            "handicap" => {
+                let corners = rest.parse::<usize>().map_err(|_| invalid())?;
+                if corners > 4 {
+                    return Err(invalid());
                 }
-                print!("{} ", content);
+                vec![self.handicap_board(corners)]
             }
-            println!("|");
+            "position" => {
+                let (board, player) = self.parse_position(rest).map_err(OpeningsError::Position)?;
+                vec![self.get_canonical_form(&board, player)]
+            }
+            _ => return Err(invalid()),
+        };
+        if openings.is_empty() {
+            return Err(OpeningsError::NoOpenings);
         }
-        println!("--------------------");
+        Ok(openings)
+    }
+
+    /// Generates up to count distinct openings in the style of XOT, by playing
+    /// plies random moves from the initial board. Openings in which the game
+    /// has ended are left out, so fewer than count are returned when there
+    /// are not enough of them.
+    pub fn random_openings(&self, count: usize, plies: usize) -> Vec<Board> {
+        let mut rng = rand::thread_rng();
+        let mut seen = HashSet::new();
+        let mut openings = Vec::new();
+        for _ in 0..count * 100 {
+            if openings.len() == count {
+                break;
+            }
+            let mut board = self.init_board;
+            let mut player = 1;
+            for _ in 0..plies {
+                let valids = self.get_valid_moves(&board, player);
+                let actions: Vec<usize> = (0..valids.len()).filter(|a| valids[*a] == 1).collect();
+                let action = *actions.choose(&mut rng).unwrap();
+                (board, player) = self.get_next_state(&board, player, self.index_to_action(action));
+                if self.get_game_ended(&board, player).is_some() {
+                    break;
+                }
+            }
+            if self.get_game_ended(&board, player).is_some() {
+                continue;
+            }
+            let opening = self.get_canonical_form(&board, player);
+            if seen.insert(opening) {
+                openings.push(opening);
+            }
+        }
+        openings
+    }
+
+    /// Returns the initial board with discs of player -1 on up to four
+    /// corners, in the order a1, h8 (the opposite corner), h1 and a8. As in
+    /// handicap games the weaker player receives the corners and the stronger
+    /// player 1 moves first.
+    pub fn handicap_board(&self, corners: usize) -> Board {
+        let (last_row, last_col) = (self.rows - 1, self.cols - 1);
+        let mut board = self.init_board;
+        for (x, y) in [(0, 0), (last_row, last_col), (0, last_col), (last_row, 0)]
+            .into_iter()
+            .take(corners)
+        {
+            board.set(x, y, -1);
+        }
+        board
+    }
+
+    /// Writes a position string: one character per square, row by row from
+    /// a1, followed by the side to move. "X" is a disc of player 1, "O" a disc
+    /// of player -1 and "-" an empty square, which for 8x8 is the 64 + 1
+    /// character format used by most Othello tools.
+    pub fn to_position_str(&self, board: &Board, player: i8) -> String {
+        let mut s = self.string_representation(board);
+        s.push_str(if player == 1 { "X" } else { "O" });
+        s
+    }
+
+    /// Parses a position string written by to_position_str. Whitespace is
+    /// ignored, and "*" for X and "." or "_" for "-" are accepted as well.
+    ///
+    /// Returns:
+    ///     (board, player): the board and the player to move
+    pub fn parse_position(&self, s: &str) -> Result<(Board, i8), PositionError> {
+        let chars: Vec<char> = s.chars().filter(|c| !c.is_whitespace()).collect();
+        let squares = self.rows * self.cols;
+        if chars.len() != squares + 1 {
+            return Err(PositionError::WrongLength {
+                expected: squares,
+                found: chars.len(),
+            });
+        }
+        let mut board = Board::empty(self.rows, self.cols);
+        for (i, c) in chars[..squares].iter().enumerate() {
+            let color = match c {
+                'X' | 'x' | '*' => 1,
+                'O' | 'o' => -1,
+                '-' | '.' | '_' => 0,
+                _ => return Err(PositionError::InvalidSquare(*c)),
+            };
+            board.set(i / self.cols, i % self.cols, color);
+        }
+        let player = match chars[squares] {
+            'X' | 'x' | '*' => 1,
+            'O' | 'o' => -1,
+            c => return Err(PositionError::InvalidSideToMove(c)),
+        };
+        Ok((board, player))
+    }
+
+    /// Checks that a board of rows x cols squares has its four center discs
+    /// in the middle, away from the handicap corners, that is both dimensions
+    /// are even and at least 4, and that it fits the 64 squares of the
+    /// bitboards. No side is then longer than 16, within the 26 column letters
+    /// of the notation.
+    pub fn check_size(rows: usize, cols: usize) -> Result<(), PositionError> {
+        if rows < 4 || cols < 4 || rows % 2 == 1 || cols % 2 == 1 || rows * cols > 64 {
+            return Err(PositionError::InvalidSize { rows, cols });
+        }
+        Ok(())
+    }
+
+    /// Reads the board argument of a command: either a board size such as
+    /// "8" or "6x8", which gives the initial position of that size, or a
+    /// position string of a square board.
+    ///
+    /// Returns:
+    ///     (game, board, player): the game and the position to start from
+    pub fn from_command_arg(arg: &str) -> Result<(Othello, Board, i8), PositionError> {
+        let size = match arg.split_once('x') {
+            Some((rows, cols)) => rows.parse::<usize>().ok().zip(cols.parse::<usize>().ok()),
+            None => arg.parse::<usize>().ok().map(|n| (n, n)),
+        };
+        if let Some((rows, cols)) = size {
+            Self::check_size(rows, cols)?;
+            let game = Othello::new_rect(rows, cols);
+            let board = *game.get_init_board();
+            return Ok((game, board, 1));
+        }
+        let squares = arg.chars().filter(|c| !c.is_whitespace()).count().max(1) - 1;
+        // the smallest square board of the string, whose length is checked
+        let n = [4, 6, 8]
+            .into_iter()
+            .find(|n| n * n >= squares)
+            .unwrap_or(8);
+        let game = Othello::new(n);
+        let (board, player) = game.parse_position(arg)?;
+        Ok((game, board, player))
+    }
+
+    fn square_content(color: i8) -> &'static str {
+        let mut content = "";
+        for tup in Self::SQUARE_CONTENT {
+            if tup.0 == color {
+                content = tup.1;
+            }
+        }
+        content
     }
 }
 
 impl Game for Othello {
+    type State = Board;
+    type Action = Move;
+    type Undo = MoveUndo;
+
     fn new(n: usize) -> Self {
-        Othello {
-            n,
-            b: Board::new(n),
-            bm: BoardMath::new(n),
+        Self::new_rect(n, n)
+    }
+
+    fn get_init_board(&self) -> &Board {
+        &self.init_board
+    }
+
+    fn get_start_board(&self) -> Board {
+        match self.openings.choose(&mut rand::thread_rng()) {
+            Some(opening) => *opening,
+            None => self.init_board,
         }
     }
 
-    fn get_init_board(&self) -> &Vec<Vec<i8>> {
-        &self.b.pieces
+    fn get_board_size(&self) -> (usize, usize) {
+        (self.rows, self.cols)
     }
 
-    fn get_board_size(&self) -> (i8, i8) {
-        (self.n as i8, self.n as i8)
+    fn get_state_size(&self, board: &Self::State) -> (usize, usize) {
+        board.size()
     }
 
     fn get_action_size(&self) -> usize {
-        self.n * self.n + 1
+        self.rows * self.cols + 1
+    }
+
+    /// Squares are numbered row by row from a1, and the pass comes last.
+    fn action_to_index(&self, action: Move) -> usize {
+        match action {
+            Move::Place(x, y) => x * self.cols + y,
+            Move::Pass => self.rows * self.cols,
+        }
+    }
+
+    fn index_to_action(&self, index: usize) -> Move {
+        if index == self.rows * self.cols {
+            Move::Pass
+        } else {
+            Move::Place(index / self.cols, index % self.cols)
+        }
     }
 
-    fn get_next_state(&self, board: &Vec<Vec<i8>>, player: i8, action: u8) -> (Vec<Vec<i8>>, i8) {
-        if action as usize == self.n * self.n {
-            return (board.clone(), -player);
+    fn get_next_state(&self, board: &Board, player: i8, action: Move) -> (Board, i8) {
+        let mut b = *board;
+        if let Move::Place(x, y) = action {
+            b.execute_move((x as i8, y as i8), player);
         }
-        let mut b = Board::new(self.n);
-        b.pieces = board.clone();
-        let m = ((action / self.n as u8) as i8, (action % self.n as u8) as i8);
-        b.execute_move(m, player);
-        (b.pieces, -player)
+        (b, -player)
+    }
+
+    fn apply(&self, board: &mut Board, player: i8, action: Move) -> (i8, MoveUndo) {
+        match action {
+            Move::Place(x, y) => (-player, board.execute_move((x as i8, y as i8), player)),
+            Move::Pass => (-player, MoveUndo::pass(player)),
+        }
+    }
+
+    fn undo(&self, board: &mut Board, undo: &MoveUndo) {
+        board.undo_move(undo);
     }
 
-    fn get_valid_moves(&self, board: &Vec<Vec<i8>>, player: i8) -> Vec<u8> {
+    fn get_valid_moves(&self, board: &Board, player: i8) -> Vec<u8> {
         let mut valids = vec![0; self.get_action_size()];
-        let mut b = Board::new(self.n);
-        b.pieces = board.clone();
-        let legal_moves = b.get_legal_moves(player);
-        if legal_moves.len() == 0 {
-            valids[self.get_action_size() - 1] = 1;
+        let legal_moves = board.get_legal_moves(player);
+        if legal_moves.is_empty() {
+            valids[self.action_to_index(Move::Pass)] = 1;
             return valids;
         }
-        for legal_move in legal_moves {
-            let i = self.n * legal_move.0 as usize + legal_move.1 as usize;
-            valids[i] = 1;
+        for (x, y) in legal_moves {
+            valids[self.action_to_index(Move::Place(x as usize, y as usize))] = 1;
         }
         valids
     }
 
-    /// return 0 if not ended, 1 if player 1 won, -1 if player 1 lost
-    /// player = 1
-    fn get_game_ended(&self, board: &Vec<Vec<i8>>, player: i8) -> i8 {
-        let mut b = Board::new(self.n);
-        b.pieces = board.clone();
-        if b.has_legal_moves(player) {
-            return 0;
+    /// return None if not ended, otherwise the result for player where empty
+    /// squares count for the winner, who has the fewest discs under
+    /// Rules::Anti
+    fn get_game_ended(&self, board: &Board, player: i8) -> Option<GameResult> {
+        if board.has_legal_moves(player) {
+            return None;
+        }
+        if board.has_legal_moves(-player) {
+            return None;
         }
-        if b.has_legal_moves(-player) {
-            return 0;
+        let (own, opp) = board.discs(player);
+        let empty = (self.rows * self.cols) as i32 - (own | opp).count_ones() as i32;
+        let diff = board.count_diff(player);
+        let margin = if diff > 0 {
+            diff + empty
+        } else if diff < 0 {
+            diff - empty
+        } else {
+            0
+        };
+        match self.rules {
+            Rules::Standard => Some(GameResult::from_margin(margin)),
+            Rules::Anti => Some(GameResult::from_margin(-margin)),
+        }
+    }
+
+    fn get_canonical_form(&self, board: &Board, player: i8) -> Board {
+        if player == 1 {
+            *board
+        } else {
+            board.inverted()
         }
-        if b.count_diff(player) > 0 {
-            return 1;
+    }
+
+    /// Returns the eight symmetries of a square board, or the four of a
+    /// rectangular one.
+    fn get_symmetries(&self, board: &Board, pi: &[f32]) -> Vec<(Board, Vec<f32>)> {
+        let pieces = board.to_pieces();
+        let mut symmetries = vec![(*board, pi.to_vec())];
+        for (t_2d, t_1d) in self.bm.symmetries() {
+            symmetries.push((
+                Board::from_pieces(&BoardMath::apply_2d(&pieces, t_2d)),
+                BoardMath::apply_1d(pi, t_1d),
+            ));
         }
-        -1
+        symmetries
     }
 
-    fn get_canonical_form(&self, board: &Vec<Vec<i8>>, player: i8) -> Vec<Vec<i8>> {
-        let mut canon = board.clone();
-        for x in 0..canon.len() {
-            for y in 0..canon[x].len() {
-                canon[x][y] *= player;
+    /// Picks the form with the least bitboards, which is cheaper than
+    /// comparing string representations.
+    fn get_canonical_symmetry(&self, board: &Board) -> (Board, usize) {
+        let pieces = board.to_pieces();
+        let mut canonical = (*board, 0);
+        for (i, (t_2d, _)) in self.bm.symmetries().into_iter().enumerate() {
+            let b = Board::from_pieces(&BoardMath::apply_2d(&pieces, t_2d));
+            if b.discs(1) < canonical.0.discs(1) {
+                canonical = (b, i + 1);
             }
         }
-        canon
+        canonical
     }
 
-    fn get_symmetries(&self, board: &Vec<Vec<i8>>, pi: &Vec<f32>) -> Vec<(Vec<Vec<i8>>, Vec<f32>)> {
-        vec![
-            (board.clone(), pi.clone()),
-            (
-                BoardMath::apply_2d(board, &self.bm.mirror_2d),
-                BoardMath::apply_1d(pi, &self.bm.mirror_1d),
-            ),
-            (
-                BoardMath::apply_2d(board, &self.bm.l90_2d),
-                BoardMath::apply_1d(pi, &self.bm.l90_1d),
-            ),
-            (
-                BoardMath::apply_2d(board, &self.bm.l90_2d_mirror),
-                BoardMath::apply_1d(pi, &self.bm.l90_1d_mirror),
-            ),
-            (
-                BoardMath::apply_2d(board, &self.bm.r90_2d),
-                BoardMath::apply_1d(pi, &self.bm.r90_1d),
-            ),
-            (
-                BoardMath::apply_2d(board, &self.bm.r90_2d_mirror),
-                BoardMath::apply_1d(pi, &self.bm.r90_1d_mirror),
-            ),
-            (
-                BoardMath::apply_2d(board, &self.bm.l180_2d),
-                BoardMath::apply_1d(pi, &self.bm.l180_1d),
-            ),
-            (
-                BoardMath::apply_2d(board, &self.bm.l180_2d_mirror),
-                BoardMath::apply_1d(pi, &self.bm.l180_1d_mirror),
-            ),
-        ]
-    }
-
-    fn string_representation(&self, board: &Vec<Vec<i8>>) -> String {
-        let mut sr = String::with_capacity(board.len() * board.len());
-        for x in 0..self.n {
-            for y in 0..self.n {
-                let mut content = "";
-                for tup in Self::SQUARE_CONTENT {
-                    if tup.0 == board[x][y] {
-                        content = tup.1;
-                    }
-                }
-                sr.push_str(content);
+    fn string_representation(&self, board: &Board) -> String {
+        let mut sr = String::with_capacity(self.rows * self.cols);
+        for x in 0..self.rows {
+            for y in 0..self.cols {
+                sr.push_str(Self::square_content(board.get(x, y)));
             }
         }
         sr
     }
+
+    fn display(&self, board: &Board) {
+        print!("   ");
+        for y in 0..self.cols {
+            print!("{} ", (b'a' + y as u8) as char);
+        }
+        println!();
+        println!("--------------------");
+        for x in 0..self.rows {
+            print!("{}| ", x + 1);
+            for y in 0..self.cols {
+                print!("{} ", Self::square_content(board.get(x, y)));
+            }
+            println!("|");
+        }
+        println!("--------------------");
+    }
+
+    /// One plane with 1 for the discs of player 1, -1 for those of player -1
+    /// and 0 for empty squares.
+    fn encode(&self, board: &Board) -> Vec<f32> {
+        let mut input = Vec::with_capacity(self.rows * self.cols);
+        for x in 0..self.rows {
+            for y in 0..self.cols {
+                input.push(board.get(x, y) as f32);
+            }
+        }
+        input
+    }
+
+    fn action_to_string(&self, action: Move) -> String {
+        notation::action_to_string(self.action_to_index(action), self.rows, self.cols)
+    }
+
+    fn string_to_action(&self, s: &str) -> Option<Move> {
+        notation::string_to_action(s, self.rows, self.cols).map(|a| self.index_to_action(a))
+    }
+
+    fn zobrist_hash(&self, board: &Board) -> u64 {
+        let (black, white) = board.discs(1);
+        self.zobrist.hash_bits(black, 1) ^ self.zobrist.hash_bits(white, -1)
+    }
+
+    fn get_next_state_hashed(
+        &self,
+        board: &Board,
+        player: i8,
+        action: Move,
+        hash: u64,
+    ) -> (Board, i8, u64) {
+        let (b, next_player) = self.get_next_state(board, player, action);
+
+        // only the placed disc and the flipped discs change the hash
+        let (black_before, white_before) = board.discs(1);
+        let (black_after, white_after) = b.discs(1);
+        let hash = hash
+            ^ self.zobrist.hash_bits(black_before ^ black_after, 1)
+            ^ self.zobrist.hash_bits(white_before ^ white_after, -1);
+        (b, next_player, hash)
+    }
+
+    fn get_canonical_hash(&self, hash: u64, player: i8) -> u64 {
+        Zobrist::canonical(hash, player)
+    }
 }
 
 #[cfg(test)]
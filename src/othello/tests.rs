@@ -1,13 +1,16 @@
 use rand::{distributions::Uniform, Rng};
 
-use super::Othello;
-use crate::game::Game;
+use super::{Move, OpeningsError, Othello, PositionError, Rules, UnknownRules};
+use crate::{
+    board::Board,
+    game::{conformance, deduplicate_examples, ActionError, Game, GameResult, Outcome},
+};
 
 #[test]
 fn get_init_board_4() {
     let othello = Othello::new(4);
     let init_board = othello.get_init_board();
-    let init_board_str = format!("{:?}", init_board);
+    let init_board_str = format!("{:?}", init_board.to_pieces());
     let expected_board = "[[0, 0, 0, 0], [0, -1, 1, 0], [0, 1, -1, 0], [0, 0, 0, 0]]";
     assert_eq!(init_board_str, expected_board);
 }
@@ -16,11 +19,21 @@ fn get_init_board_4() {
 fn get_init_board_6() {
     let othello = Othello::new(6);
     let init_board = othello.get_init_board();
-    let init_board_str = format!("{:?}", init_board);
+    let init_board_str = format!("{:?}", init_board.to_pieces());
     let expected_board = "[[0, 0, 0, 0, 0, 0], [0, 0, 0, 0, 0, 0], [0, 0, -1, 1, 0, 0], [0, 0, 1, -1, 0, 0], [0, 0, 0, 0, 0, 0], [0, 0, 0, 0, 0, 0]]";
     assert_eq!(init_board_str, expected_board);
 }
 
+#[test]
+fn get_init_board_4x6() {
+    let othello = Othello::new_rect(4, 6);
+    let init_board = othello.get_init_board();
+    let init_board_str = format!("{:?}", init_board.to_pieces());
+    let expected_board =
+        "[[0, 0, 0, 0, 0, 0], [0, 0, -1, 1, 0, 0], [0, 0, 1, -1, 0, 0], [0, 0, 0, 0, 0, 0]]";
+    assert_eq!(init_board_str, expected_board);
+}
+
 #[test]
 fn get_board_size_4() {
     let othello = Othello::new(4);
@@ -49,18 +62,40 @@ fn get_action_size_6() {
     assert_eq!(action_size, 37);
 }
 
+#[test]
+fn get_action_size_4x6() {
+    let othello = Othello::new_rect(4, 6);
+    assert_eq!(othello.get_board_size(), (4, 6));
+    assert_eq!(othello.get_action_size(), 25);
+}
+
+#[test]
+fn action_index_round_trip() {
+    let othello = Othello::new_rect(4, 6);
+    for index in 0..othello.get_action_size() {
+        assert_eq!(
+            othello.action_to_index(othello.index_to_action(index)),
+            index
+        );
+    }
+    assert_eq!(othello.index_to_action(7), Move::Place(1, 1));
+    assert_eq!(othello.index_to_action(24), Move::Pass);
+    assert_eq!(othello.string_to_action("b2"), Some(Move::Place(1, 1)));
+    assert_eq!(othello.action_to_string(Move::Pass), "pass");
+}
+
 #[test]
 fn get_next_state_4() {
     let othello = Othello::new(4);
     let board = othello.get_init_board();
-    let next_state = othello.get_next_state(board, 1, 1);
-    let next_state_str = format!("{:?}", next_state);
+    let next_state = othello.get_next_state(board, 1, Move::Place(0, 1));
+    let next_state_str = format!("{:?}", (next_state.0.to_pieces(), next_state.1));
     let expected_next_state = "([[0, 1, 0, 0], [0, 1, 1, 0], [0, 1, -1, 0], [0, 0, 0, 0]], -1)";
     assert_eq!(next_state_str, expected_next_state.to_string());
 
     let board = &next_state.0;
-    let next_state = othello.get_next_state(board, -1, 0);
-    let next_state_str = format!("{:?}", next_state);
+    let next_state = othello.get_next_state(board, -1, Move::Place(0, 0));
+    let next_state_str = format!("{:?}", (next_state.0.to_pieces(), next_state.1));
     let expected_next_state = "([[-1, 1, 0, 0], [0, -1, 1, 0], [0, 1, -1, 0], [0, 0, 0, 0]], 1)";
     assert_eq!(next_state_str, expected_next_state.to_string());
 }
@@ -69,12 +104,49 @@ fn get_next_state_4() {
 fn get_next_state_illegal() {
     let othello = Othello::new(4);
     let board = othello.get_init_board();
-    let next_state = othello.get_next_state(board, 1, 6);
-    let next_state_str = format!("{:?}", next_state);
-    let expected_next_state = "([[0, 1, 0, 0], [0, 1, 1, 0], [0, 1, -1, 0], [0, 0, 0, 0]], -1)";
+    // c2 is taken: get_next_state does not check moves, and places nothing
+    let next_state = othello.get_next_state(board, 1, Move::Place(1, 2));
+    let next_state_str = format!("{:?}", (next_state.0.to_pieces(), next_state.1));
+    let expected_next_state = "([[0, 0, 0, 0], [0, -1, 1, 0], [0, 1, -1, 0], [0, 0, 0, 0]], -1)";
     assert_eq!(next_state_str, expected_next_state.to_string());
 }
 
+#[test]
+fn try_next_state_illegal() {
+    let othello = Othello::new(4);
+    let board = othello.get_init_board();
+    assert_eq!(
+        othello.try_next_state(board, 1, Move::Place(1, 2)),
+        Err(ActionError::Illegal {
+            action: "c2".to_owned(),
+            player: 1
+        })
+    );
+    assert_eq!(
+        othello.try_next_state(board, 1, Move::Pass),
+        Err(ActionError::Illegal {
+            action: "pass".to_owned(),
+            player: 1
+        })
+    );
+    // (0, 4) would be index 4, which is a2
+    for action in [Move::Place(0, 4), Move::Place(4, 0)] {
+        assert!(matches!(
+            othello.try_next_state(board, 1, action),
+            Err(ActionError::OutOfRange(_))
+        ));
+    }
+    assert_eq!(
+        othello.try_next_state(board, 1, Move::Place(0, 1)),
+        Ok(othello.get_next_state(board, 1, Move::Place(0, 1)))
+    );
+    let full = Board::from_pieces(&vec![vec![1; 4]; 4]);
+    assert_eq!(
+        othello.try_next_state(&full, -1, Move::Pass),
+        Err(ActionError::GameOver)
+    );
+}
+
 #[test]
 fn get_valid_moves_4() {
     let othello = Othello::new(4);
@@ -85,18 +157,163 @@ fn get_valid_moves_4() {
     assert_eq!(valid_moves_str, valid_moves_expected);
 }
 
+#[test]
+fn encode_4() {
+    let othello = Othello::new(4);
+    let board = othello.get_init_board();
+    let expected = [
+        0., 0., 0., 0., 0., -1., 1., 0., 0., 1., -1., 0., 0., 0., 0., 0.,
+    ];
+    assert_eq!(othello.encode(board), expected);
+    let inverted = othello.get_canonical_form(board, -1);
+    let expected_inverted: Vec<f32> = expected.iter().map(|v| -v).collect();
+    assert_eq!(othello.encode(&inverted), expected_inverted);
+}
+
 #[test]
 fn get_game_ended_4() {
     let othello = Othello::new(4);
-    let board = [
+    let board: Board = [
         [0, -1, -1, -1].to_vec(),
         [-1, -1, 1, -1].to_vec(),
         [0, -1, -1, -1].to_vec(),
         [-1, 0, -1, -1].to_vec(),
     ]
-    .to_vec();
+    .to_vec()
+    .try_into()
+    .unwrap();
     let game_ended = othello.get_game_ended(&board, 1);
-    assert_eq!(game_ended, -1);
+    assert_eq!(
+        game_ended,
+        Some(GameResult {
+            outcome: Outcome::Loss,
+            margin: -14,
+            forfeit: None
+        })
+    );
+    let game_ended = othello.get_game_ended(&board, -1);
+    assert_eq!(
+        game_ended,
+        Some(GameResult {
+            outcome: Outcome::Win,
+            margin: 14,
+            forfeit: None
+        })
+    );
+}
+
+#[test]
+fn get_game_ended_draw() {
+    let othello = Othello::new(4);
+    let board: Board = [
+        [1, 1, 1, 1].to_vec(),
+        [1, 1, 1, 1].to_vec(),
+        [-1, -1, -1, -1].to_vec(),
+        [-1, -1, -1, -1].to_vec(),
+    ]
+    .to_vec()
+    .try_into()
+    .unwrap();
+    let game_ended = othello.get_game_ended(&board, 1).unwrap();
+    assert_eq!(game_ended.outcome, Outcome::Draw);
+    assert_eq!(game_ended.margin, 0);
+    assert_eq!(game_ended.value(0.25), 0.25);
+    assert_eq!(game_ended.for_opponent().value(0.25), 0.25);
+}
+
+#[test]
+fn get_game_ended_not_ended() {
+    let othello = Othello::new(4);
+    let board = othello.get_init_board();
+    assert_eq!(othello.get_game_ended(board, 1), None);
+    assert_eq!(othello.get_game_ended(board, -1), None);
+}
+
+#[test]
+fn get_game_ended_anti_4() {
+    let othello = Othello::new(4).with_rules(Rules::Anti);
+    let board: Board = [
+        [0, -1, -1, -1].to_vec(),
+        [-1, -1, 1, -1].to_vec(),
+        [0, -1, -1, -1].to_vec(),
+        [-1, 0, -1, -1].to_vec(),
+    ]
+    .to_vec()
+    .try_into()
+    .unwrap();
+    // player 1 has one disc against twelve, and the three empty squares go to
+    // the winner
+    let game_ended = othello.get_game_ended(&board, 1);
+    assert_eq!(
+        game_ended,
+        Some(GameResult {
+            outcome: Outcome::Win,
+            margin: 14,
+            forfeit: None
+        })
+    );
+    let game_ended = othello.get_game_ended(&board, -1);
+    assert_eq!(
+        game_ended,
+        Some(GameResult {
+            outcome: Outcome::Loss,
+            margin: -14,
+            forfeit: None
+        })
+    );
+}
+
+#[test]
+fn get_game_ended_anti_draw() {
+    let othello = Othello::new(4).with_rules(Rules::Anti);
+    let board: Board = [
+        [1, 1, 1, 1].to_vec(),
+        [1, 1, 1, 1].to_vec(),
+        [-1, -1, -1, -1].to_vec(),
+        [-1, -1, -1, -1].to_vec(),
+    ]
+    .to_vec()
+    .try_into()
+    .unwrap();
+    let game_ended = othello.get_game_ended(&board, 1).unwrap();
+    assert_eq!(game_ended.outcome, Outcome::Draw);
+    assert_eq!(game_ended.margin, 0);
+}
+
+#[test]
+fn anti_shares_moves() {
+    let othello = Othello::new(6);
+    let anti = Othello::new(6).with_rules(Rules::Anti);
+    let mut rng = rand::thread_rng();
+    let mut board = *othello.get_init_board();
+    let mut player = 1;
+    loop {
+        let valids = othello.get_valid_moves(&board, player);
+        assert_eq!(anti.get_valid_moves(&board, player), valids);
+        match othello.get_game_ended(&board, player) {
+            Some(result) => {
+                assert_eq!(
+                    anti.get_game_ended(&board, player),
+                    Some(result.for_opponent())
+                );
+                break;
+            }
+            None => assert_eq!(anti.get_game_ended(&board, player), None),
+        }
+        let actions: Vec<usize> = (0..valids.len()).filter(|a| valids[*a] == 1).collect();
+        let action = actions[rng.gen_range(0..actions.len())];
+        (board, player) = othello.get_next_state(&board, player, othello.index_to_action(action));
+    }
+}
+
+#[test]
+fn rules_from_str() {
+    assert_eq!("standard".parse(), Ok(Rules::Standard));
+    assert_eq!("Anti".parse(), Ok(Rules::Anti));
+    assert_eq!(
+        "misere".parse::<Rules>(),
+        Err(UnknownRules("misere".to_owned()))
+    );
 }
 
 #[test]
@@ -123,6 +340,316 @@ fn mask_probability_vector_4() {
     println!("sum_ps_s: {:?}", sum_ps_s);
 }
 
+#[test]
+fn zobrist_hash_incremental() {
+    let othello = Othello::new(6);
+    let mut rng = rand::thread_rng();
+    for _ in 0..20 {
+        let mut board = *othello.get_init_board();
+        let mut player = 1;
+        let mut hash = othello.zobrist_hash(&board);
+        while othello.get_game_ended(&board, player).is_none() {
+            let valids = othello.get_valid_moves(&board, player);
+            let actions: Vec<usize> = (0..valids.len()).filter(|a| valids[*a] == 1).collect();
+            let action = actions[rng.gen_range(0..actions.len())];
+            let next_state = othello.get_next_state_hashed(
+                &board,
+                player,
+                othello.index_to_action(action),
+                hash,
+            );
+            assert_eq!(next_state.2, othello.zobrist_hash(&next_state.0));
+            (board, player, hash) = next_state;
+
+            let canon = othello.get_canonical_form(&board, player);
+            let canon_hash = othello.get_canonical_hash(hash, player);
+            assert_eq!(canon_hash, othello.zobrist_hash(&canon));
+        }
+    }
+}
+
+#[test]
+fn apply_undo_round_trip() {
+    let othello = Othello::new(6);
+    let mut rng = rand::thread_rng();
+    for _ in 0..20 {
+        let mut board = *othello.get_init_board();
+        let mut player = 1;
+        let mut history = Vec::new();
+        while othello.get_game_ended(&board, player).is_none() {
+            let valids = othello.get_valid_moves(&board, player);
+            let actions: Vec<usize> = (0..valids.len()).filter(|a| valids[*a] == 1).collect();
+            let action = othello.index_to_action(actions[rng.gen_range(0..actions.len())]);
+            let expected = othello.get_next_state(&board, player, action);
+            let before = board;
+            let (next_player, undo) = othello.apply(&mut board, player, action);
+            assert_eq!((board, next_player), expected);
+            history.push((before, undo));
+            player = next_player;
+        }
+        // unwinding the whole game gives back every position on the way
+        while let Some((before, undo)) = history.pop() {
+            othello.undo(&mut board, &undo);
+            assert_eq!(board, before);
+        }
+        assert_eq!(&board, othello.get_init_board());
+    }
+}
+
+/// Every symmetry of a position must map its valid moves onto the valid moves
+/// of the transformed position.
+fn assert_symmetries_consistent(othello: &Othello, count: usize) {
+    let mut rng = rand::thread_rng();
+    let mut board = *othello.get_init_board();
+    let mut player = 1;
+    while othello.get_game_ended(&board, player).is_none() {
+        let valids = othello.get_valid_moves(&board, player);
+        let pi: Vec<f32> = valids.iter().map(|v| *v as f32).collect();
+        let symmetries = othello.get_symmetries(&board, &pi);
+        assert_eq!(symmetries.len(), count);
+        for (sym_board, sym_pi) in symmetries {
+            let sym_valids: Vec<f32> = othello
+                .get_valid_moves(&sym_board, player)
+                .iter()
+                .map(|v| *v as f32)
+                .collect();
+            assert_eq!(sym_valids, sym_pi);
+        }
+
+        let actions: Vec<usize> = (0..valids.len()).filter(|a| valids[*a] == 1).collect();
+        let action = actions[rng.gen_range(0..actions.len())];
+        (board, player) = othello.get_next_state(&board, player, othello.index_to_action(action));
+    }
+}
+
+#[test]
+fn get_symmetries_square() {
+    assert_symmetries_consistent(&Othello::new(6), 8);
+}
+
+#[test]
+fn get_symmetries_rect() {
+    assert_symmetries_consistent(&Othello::new_rect(4, 6), 4);
+    assert_symmetries_consistent(&Othello::new_rect(8, 6), 4);
+}
+
+#[test]
+fn random_openings_8() {
+    let othello = Othello::new(8);
+    let openings = othello.random_openings(50, 8);
+    assert_eq!(openings.len(), 50);
+    for (i, opening) in openings.iter().enumerate() {
+        let discs = opening
+            .to_pieces()
+            .iter()
+            .flatten()
+            .filter(|s| **s != 0)
+            .count();
+        assert_eq!(discs, 12);
+        assert_eq!(othello.get_game_ended(opening, 1), None);
+        assert!(!openings[..i].contains(opening));
+    }
+
+    let othello = othello.with_openings(openings.clone());
+    for _ in 0..20 {
+        assert!(openings.contains(&othello.get_start_board()));
+    }
+}
+
+#[test]
+fn random_openings_exhausted() {
+    // only four positions exist after one ply
+    let othello = Othello::new(4);
+    assert_eq!(othello.random_openings(10, 1).len(), 4);
+}
+
+#[test]
+fn get_start_board_default() {
+    let othello = Othello::new(6);
+    assert_eq!(&othello.get_start_board(), othello.get_init_board());
+}
+
+#[test]
+fn handicap_board_4() {
+    let othello = Othello::new(4);
+    let board = othello.handicap_board(2);
+    let expected_board = "[[-1, 0, 0, 0], [0, -1, 1, 0], [0, 1, -1, 0], [0, 0, 0, -1]]";
+    assert_eq!(format!("{:?}", board.to_pieces()), expected_board);
+    let board = othello.handicap_board(4);
+    let expected_board = "[[-1, 0, 0, -1], [0, -1, 1, 0], [0, 1, -1, 0], [-1, 0, 0, -1]]";
+    assert_eq!(format!("{:?}", board.to_pieces()), expected_board);
+}
+
+#[test]
+fn openings_from_arg() {
+    let othello = Othello::new(4);
+    assert_eq!(othello.openings_from_arg("standard"), Ok(Vec::new()));
+    assert_eq!(othello.openings_from_arg("random:3:2").unwrap().len(), 3);
+    assert_eq!(
+        othello.openings_from_arg("handicap:1"),
+        Ok(vec![othello.handicap_board(1)])
+    );
+
+    // player -1 to move is turned into the same position with player 1 to move
+    let inverted = othello.get_canonical_form(othello.get_init_board(), -1);
+    assert_eq!(
+        othello.openings_from_arg("position:-----OX--XO-----O"),
+        Ok(vec![inverted])
+    );
+
+    for arg in ["random:3", "random:x:2", "handicap:5", "xot", "standard:1"] {
+        assert_eq!(
+            othello.openings_from_arg(arg),
+            Err(OpeningsError::InvalidArg(arg.to_owned()))
+        );
+    }
+    assert_eq!(
+        othello.openings_from_arg("position:-----OX--XO-----"),
+        Err(OpeningsError::Position(PositionError::WrongLength {
+            expected: 16,
+            found: 16
+        }))
+    );
+    assert_eq!(
+        othello.openings_from_arg("random:0:2"),
+        Err(OpeningsError::NoOpenings)
+    );
+}
+
+#[test]
+fn zobrist_hash_distinguishes_colors() {
+    let othello = Othello::new(4);
+    let board = othello.get_init_board();
+    let inverted = othello.get_canonical_form(board, -1);
+    assert_ne!(othello.zobrist_hash(board), othello.zobrist_hash(&inverted));
+    assert_ne!(othello.zobrist_hash(board), 0);
+}
+
+#[test]
+fn position_str_8() {
+    let othello = Othello::new(8);
+    let board = othello.get_init_board();
+    let position = othello.to_position_str(board, 1);
+    assert_eq!(
+        position,
+        "---------------------------OX------XO---------------------------X"
+    );
+    assert_eq!(othello.parse_position(&position), Ok((*board, 1)));
+}
+
+#[test]
+fn position_str_round_trip() {
+    let othello = Othello::new(6);
+    let mut rng = rand::thread_rng();
+    let mut board = *othello.get_init_board();
+    let mut player = 1;
+    while othello.get_game_ended(&board, player).is_none() {
+        let position = othello.to_position_str(&board, player);
+        assert_eq!(position.len(), 37);
+        assert_eq!(othello.parse_position(&position), Ok((board, player)));
+
+        let valids = othello.get_valid_moves(&board, player);
+        let actions: Vec<usize> = (0..valids.len()).filter(|a| valids[*a] == 1).collect();
+        let action = actions[rng.gen_range(0..actions.len())];
+        (board, player) = othello.get_next_state(&board, player, othello.index_to_action(action));
+    }
+}
+
+#[test]
+fn position_str_variants() {
+    let othello = Othello::new(4);
+    let (board, player) = othello.parse_position("....\n.O*.\n.*o.\n.... O").unwrap();
+    assert_eq!(&board, othello.get_init_board());
+    assert_eq!(player, -1);
+}
+
+#[test]
+fn position_str_invalid() {
+    let othello = Othello::new(4);
+    assert_eq!(
+        othello.parse_position("-----OX--XO-----"),
+        Err(PositionError::WrongLength {
+            expected: 16,
+            found: 16
+        })
+    );
+    assert_eq!(
+        othello.parse_position("-----OX--XO----#X"),
+        Err(PositionError::InvalidSquare('#'))
+    );
+    assert_eq!(
+        othello.parse_position("-----OX--XO-----Y"),
+        Err(PositionError::InvalidSideToMove('Y'))
+    );
+}
+
+#[test]
+fn from_command_arg() {
+    let (othello, board, player) = Othello::from_command_arg("6").unwrap();
+    assert_eq!(othello.get_board_size(), (6, 6));
+    assert_eq!(&board, othello.get_init_board());
+    assert_eq!(player, 1);
+
+    let (othello, board, player) = Othello::from_command_arg("4x6").unwrap();
+    assert_eq!(othello.get_board_size(), (4, 6));
+    assert_eq!(&board, othello.get_init_board());
+    assert_eq!(player, 1);
+
+    let (othello, board, player) = Othello::from_command_arg("-----OX--XO-----O").unwrap();
+    assert_eq!(othello.get_board_size(), (4, 4));
+    assert_eq!(&board, othello.get_init_board());
+    assert_eq!(player, -1);
+}
+
+#[test]
+fn invalid_board_sizes_are_rejected() {
+    for (arg, rows, cols) in [
+        ("1", 1, 1),
+        ("5", 5, 5),
+        ("3x5", 3, 5),
+        ("10", 10, 10),
+        ("2x9", 2, 9),
+        ("2", 2, 2),
+        ("2x8", 2, 8),
+        ("4x18", 4, 18),
+    ] {
+        assert_eq!(
+            Othello::from_command_arg(arg).err(),
+            Some(PositionError::InvalidSize { rows, cols })
+        );
+    }
+    assert!(Othello::check_size(2, 32).is_err());
+    assert!(Othello::check_size(4, 16).is_ok());
+    // a position of 25 squares is too long for 4x4 and too short for 6x6
+    assert_eq!(
+        Othello::from_command_arg(&format!("{}X", "-".repeat(25))).err(),
+        Some(PositionError::WrongLength {
+            expected: 36,
+            found: 26
+        })
+    );
+}
+
+#[test]
+fn widest_boards_round_trip() {
+    for othello in [Othello::new_rect(4, 16), Othello::new_rect(16, 4)] {
+        for a in 0..othello.get_action_size() {
+            let action = othello.index_to_action(a);
+            let s = othello.action_to_string(action);
+            assert_eq!(othello.string_to_action(&s), Some(action), "{s}");
+        }
+        let board = othello.handicap_board(4);
+        let position = othello.to_position_str(&board, -1);
+        assert_eq!(othello.parse_position(&position), Ok((board, -1)));
+    }
+}
+
+#[test]
+#[should_panic(expected = "5x5 is not a board size")]
+fn odd_boards_panic() {
+    Othello::new(5);
+}
+
 #[test]
 fn rand_index() {
     let rand_generator = rand::thread_rng();
@@ -192,3 +719,75 @@ fn rand_index() {
 //     b[n / 2][n / 2] = 1;
 //     b
 // }
+
+#[test]
+fn conformance() {
+    for othello in [Othello::new(4), Othello::new(6), Othello::new_rect(4, 6)] {
+        conformance::check(&othello, 20);
+    }
+    conformance::check(&Othello::new(8).with_rules(Rules::Anti), 5);
+    let othello = Othello::new(6);
+    let openings = othello.random_openings(10, 4);
+    conformance::check(&othello.with_openings(openings), 10);
+}
+
+#[test]
+fn canonical_symmetry_of_first_moves() {
+    let othello = Othello::new(8);
+    let board = othello.get_init_board();
+    // the four first moves lead to symmetrical positions
+    let boards: Vec<Board> = ["d3", "c4", "f5", "e6"]
+        .iter()
+        .map(|m| {
+            let action = othello.string_to_action(m).unwrap();
+            othello.get_next_state(board, 1, action).0
+        })
+        .collect();
+    let (canonical, _) = othello.get_canonical_symmetry(&boards[0]);
+    for b in &boards {
+        let (c, symmetry) = othello.get_canonical_symmetry(b);
+        assert_eq!(c, canonical);
+        assert_eq!(othello.get_symmetries(b, &[0.; 65])[symmetry].0, c);
+    }
+    // a policy on the canonical board maps back to the board
+    let (_, symmetry) = othello.get_canonical_symmetry(&boards[2]);
+    let mut pi = vec![0.; 65];
+    let valids = othello.get_valid_moves(&canonical, -1);
+    let a = valids.iter().position(|v| *v == 1).unwrap();
+    pi[a] = 1.;
+    let original = othello.policy_from_symmetry(symmetry, &pi);
+    let b = original.iter().position(|p| *p == 1.).unwrap();
+    assert_eq!(othello.get_valid_moves(&boards[2], -1)[b], 1);
+    assert_eq!(original[64], 0.);
+}
+
+#[test]
+fn deduplicate_symmetrical_examples() {
+    let othello = Othello::new(6);
+    let (board, _) = othello
+        .parse_position(&format!(
+            "{}X",
+            "-".repeat(12) + "--OX----XXX-----" + &"-".repeat(8)
+        ))
+        .unwrap();
+    let mut pi = vec![0.; 37];
+    pi[8] = 0.5;
+    pi[9] = 0.5;
+    let mut examples = Vec::new();
+    for (v, (b, p)) in othello.get_symmetries(&board, &pi).into_iter().enumerate() {
+        examples.push((b, p, if v % 2 == 0 { 1. } else { 0. }));
+    }
+    let other = *othello.get_init_board();
+    examples.push((other, vec![1. / 37.; 37], -1.));
+
+    let deduplicated = deduplicate_examples(&othello, &examples);
+    // the symmetries of both positions, the initial board having four of them
+    assert_eq!(deduplicated.len(), 16);
+    let (canonical, symmetry) = othello.get_canonical_symmetry(&board);
+    let expected = othello.get_symmetries(&board, &pi).swap_remove(symmetry).1;
+    let (b, p, v) = &deduplicated[0];
+    assert_eq!(*b, canonical);
+    assert_eq!(*p, expected);
+    assert_eq!(*v, 0.5);
+    assert!(deduplicated[8..].iter().all(|(_, _, v)| *v == -1.));
+}
@@ -7,7 +7,7 @@ use burn::{
     },
     tensor::{
         activation::{log_softmax, relu, tanh},
-        backend::{AutodiffBackend, Backend},
+        backend::Backend,
         Float, Tensor,
     },
 };
@@ -30,15 +30,18 @@ pub struct Model<B: Backend> {
     fc4: Linear<B>,
     dropout: Dropout,
     num_channels: usize,
+    action_size: usize,
 }
 
+/// The board size and the action size come from the game, see
+/// `Game::get_board_size` and `Game::get_action_size`. The last two
+/// convolutions have no padding, and shrink the board by 4 squares in each
+/// dimension, except in a dimension of fewer than 5 squares, where they are
+/// padded to keep its size.
 #[derive(Config, Debug)]
 pub struct ModelConfig {
-    #[config(default = "6")]
-    board_x: i8,
-    #[config(default = "6")]
-    board_y: i8,
-    #[config(default = "37")]
+    board_x: usize,
+    board_y: usize,
     action_size: usize,
     #[config(default = "6")]
     num_classes: usize,
@@ -53,6 +56,15 @@ pub struct ModelConfig {
 impl ModelConfig {
     /// Returns the initialized model.
     pub fn init<B: Backend>(&self, device: &B::Device) -> Model<B> {
+        assert!(
+            self.board_x > 0 && self.board_y > 0,
+            "a board of {}x{} squares has no squares",
+            self.board_x,
+            self.board_y
+        );
+        let (pad_x, pad_y) = (Self::padding(self.board_x), Self::padding(self.board_y));
+        let shrunk = |n: usize, pad: usize| if pad == 0 { n - 4 } else { n };
+        let features = shrunk(self.board_x, pad_x) * shrunk(self.board_y, pad_y);
         Model {
             conv1: Conv2dConfig::new([1, self.num_channels], [3, 3])
                 .with_padding(PaddingConfig2d::Explicit(1, 1))
@@ -60,17 +72,17 @@ impl ModelConfig {
             conv2: Conv2dConfig::new([self.num_channels, self.num_channels], [3, 3])
                 .with_padding(PaddingConfig2d::Explicit(1, 1))
                 .init(device),
-            conv3: Conv2dConfig::new([self.num_channels, self.num_channels], [3, 3]).init(device),
-            conv4: Conv2dConfig::new([self.num_channels, self.num_channels], [3, 3]).init(device),
+            conv3: Conv2dConfig::new([self.num_channels, self.num_channels], [3, 3])
+                .with_padding(PaddingConfig2d::Explicit(pad_x, pad_y))
+                .init(device),
+            conv4: Conv2dConfig::new([self.num_channels, self.num_channels], [3, 3])
+                .with_padding(PaddingConfig2d::Explicit(pad_x, pad_y))
+                .init(device),
             bn1: BatchNormConfig::new(self.num_channels).init(device),
             bn2: BatchNormConfig::new(self.num_channels).init(device),
             bn3: BatchNormConfig::new(self.num_channels).init(device),
             bn4: BatchNormConfig::new(self.num_channels).init(device),
-            fc1: LinearConfig::new(
-                self.num_channels * ((self.board_x - 4) * (self.board_y - 4)) as usize,
-                1024,
-            )
-            .init(device),
+            fc1: LinearConfig::new(self.num_channels * features, 1024).init(device),
             fc_bn1: BatchNormConfig::new(1024).init(device),
             fc2: LinearConfig::new(1024, 512).init(device),
             fc_bn2: BatchNormConfig::new(512).init(device),
@@ -78,11 +90,21 @@ impl ModelConfig {
             fc4: LinearConfig::new(512, 1).init(device),
             dropout: DropoutConfig::new(self.dropout).init(),
             num_channels: self.num_channels,
+            action_size: self.action_size,
+        }
+    }
+
+    /// Padding of the last two convolutions in a dimension of n squares.
+    fn padding(n: usize) -> usize {
+        if n < 5 {
+            1
+        } else {
+            0
         }
     }
 }
 
-impl<B: AutodiffBackend> Model<B> {
+impl<B: Backend> Model<B> {
     pub fn forward(&self, images: Tensor<B, 3, Float>) -> (Tensor<B, 2>, Tensor<B, 2>) {
         let [batch_size, board_x, board_y] = images.dims(); // batch_size x board_x x board_y
 
@@ -92,10 +114,8 @@ impl<B: AutodiffBackend> Model<B> {
         let s = relu(self.bn2.forward(self.conv2.forward(s)));
         let s = relu(self.bn3.forward(self.conv3.forward(s)));
         let s = relu(self.bn4.forward(self.conv4.forward(s)));
-        let s = s.reshape([
-            -1,
-            (self.num_channels * (board_x - 4) * (board_y - 4)) as i32,
-        ]);
+        // the convolutions may have shrunk the board, see ModelConfig
+        let s = s.reshape([batch_size as i32, -1]);
 
         let s = self.dropout.forward(relu(
             self.fc_bn1
@@ -113,7 +133,7 @@ impl<B: AutodiffBackend> Model<B> {
         let pi = self
             .fc3
             .forward(s.clone().reshape([batch_size as i32, -1]))
-            .reshape([batch_size, board_x * board_y + 1]); // batch_size x action_size
+            .reshape([batch_size, self.action_size]); // batch_size x action_size
         let v = self
             .fc4
             .forward(s.clone().reshape([batch_size as i32, -1]))
@@ -122,3 +142,6 @@ impl<B: AutodiffBackend> Model<B> {
         (log_softmax(pi, 1), tanh(v))
     }
 }
+
+#[cfg(test)]
+mod tests;
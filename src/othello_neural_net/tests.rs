@@ -0,0 +1,18 @@
+use burn::{backend::LibTorch, tensor::Tensor};
+
+use super::ModelConfig;
+
+#[test]
+fn forward_on_small_and_large_boards() {
+    let device = Default::default();
+    for (board_x, board_y) in [(3, 3), (4, 6), (6, 7)] {
+        let action_size = board_x * board_y;
+        let model = ModelConfig::new(board_x, board_y, action_size)
+            .with_num_channels(4)
+            .init::<LibTorch>(&device);
+        let boards = Tensor::<LibTorch, 3>::zeros([2, board_x, board_y], &device);
+        let (pi, v) = model.forward(boards);
+        assert_eq!(pi.dims(), [2, action_size]);
+        assert_eq!(v.dims(), [2, 1]);
+    }
+}
@@ -0,0 +1,78 @@
+use std::time::SystemTime;
+
+use crate::{game::Game, othello::Othello};
+
+/// Counts the leaf nodes of the game tree below board, depth plies deep.
+///
+/// A pass is a ply of its own and a finished game counts as a single leaf, which
+/// is how the published Othello perft numbers are counted. The board is walked
+/// with apply/undo and is back in its original state on return.
+pub fn perft<G: Game>(game: &G, board: &mut G::State, player: i8, depth: usize) -> u64 {
+    if depth == 0 || game.get_game_ended(board, player).is_some() {
+        return 1;
+    }
+    let valids = game.get_valid_moves(board, player);
+    let mut nodes = 0;
+    for (action, valid) in valids.iter().enumerate() {
+        if *valid == 0 {
+            continue;
+        }
+        let (next_player, undo) = game.apply(board, player, game.index_to_action(action));
+        nodes += perft(game, board, next_player, depth - 1);
+        game.undo(board, &undo);
+    }
+    nodes
+}
+
+/// Same as perft, but returns the count below every valid action separately.
+pub fn divide<G: Game>(
+    game: &G,
+    board: &mut G::State,
+    player: i8,
+    depth: usize,
+) -> Vec<(G::Action, u64)> {
+    let valids = game.get_valid_moves(board, player);
+    let mut counts = Vec::new();
+    for (action, valid) in valids.iter().enumerate() {
+        if *valid == 0 {
+            continue;
+        }
+        let action = game.index_to_action(action);
+        let (next_player, undo) = game.apply(board, player, action);
+        counts.push((action, perft(game, board, next_player, depth - 1)));
+        game.undo(board, &undo);
+    }
+    counts
+}
+
+/// Runs `othello perft <depth> [board size | position] [divide]`, printing the
+/// node count for every depth up to depth from the initial position, or from
+/// the given position string. With divide, the count at depth is also printed
+/// per action.
+pub fn command(args: &[String]) {
+    let depth = args
+        .first()
+        .expect("Usage: othello perft <depth> [board size | position] [divide]")
+        .parse::<usize>()
+        .expect("depth should be a number");
+    let (game, mut board, player) =
+        match Othello::from_command_arg(args.get(1).map_or("8", |v| v.as_str())) {
+            Ok(position) => position,
+            Err(e) => return println!("Invalid position: {e}"),
+        };
+
+    for d in 1..depth + 1 {
+        let now = SystemTime::now();
+        let nodes = perft(&game, &mut board, player, d);
+        let elapsed = now.elapsed().map_or(0., |e| e.as_secs_f32());
+        println!("perft({d}) = {nodes} ({elapsed:.3} seconds)");
+    }
+    if args.get(2).is_some_and(|v| v == "divide") {
+        for (action, nodes) in divide(&game, &mut board, player, depth) {
+            println!("{}: {nodes}", game.action_to_string(action));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
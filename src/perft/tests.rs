@@ -0,0 +1,87 @@
+use super::{divide, perft};
+use crate::{board::Board, game::Game, othello::Othello};
+
+/// Clone-based perft through get_next_state, to check apply/undo against.
+fn perft_cloning(othello: &Othello, board: &Board, player: i8, depth: usize) -> u64 {
+    if depth == 0 || othello.get_game_ended(board, player).is_some() {
+        return 1;
+    }
+    let valids = othello.get_valid_moves(board, player);
+    let mut nodes = 0;
+    for (action, _) in valids.iter().enumerate().filter(|(_, v)| **v == 1) {
+        let action = othello.index_to_action(action);
+        let (next_board, next_player) = othello.get_next_state(board, player, action);
+        nodes += perft_cloning(othello, &next_board, next_player, depth - 1);
+    }
+    nodes
+}
+
+fn perft_counts(n: usize, depth: usize) -> Vec<u64> {
+    let othello = Othello::new(n);
+    let mut board = *othello.get_init_board();
+    let counts = (1..depth + 1)
+        .map(|d| perft(&othello, &mut board, 1, d))
+        .collect();
+    assert_eq!(&board, othello.get_init_board());
+    counts
+}
+
+#[test]
+fn perft_8() {
+    // published counts for the standard 8x8 starting position
+    assert_eq!(perft_counts(8, 7), vec![4, 12, 56, 244, 1396, 8200, 55092]);
+}
+
+#[test]
+fn perft_8_with_passes() {
+    // published counts again, with the first passes and game ends of the
+    // tree at depths 8 and 9, a pass counting as a ply
+    assert_eq!(
+        perft_counts(8, 9),
+        vec![4, 12, 56, 244, 1396, 8200, 55092, 390216, 3005288]
+    );
+}
+
+#[test]
+fn perft_6() {
+    assert_eq!(perft_counts(6, 7), vec![4, 12, 56, 244, 1364, 7604, 47740]);
+}
+
+#[test]
+fn perft_4() {
+    assert_eq!(
+        perft_counts(4, 16),
+        vec![
+            4, 12, 44, 128, 424, 1256, 3624, 9116, 20044, 36540, 50704, 57436, 59564, 59980, 60060,
+            60060
+        ]
+    );
+}
+
+#[test]
+fn perft_matches_cloning() {
+    for (n, depth) in [(4, 12), (6, 5), (8, 4)] {
+        let othello = Othello::new(n);
+        let mut board = *othello.get_init_board();
+        assert_eq!(
+            perft(&othello, &mut board, 1, depth),
+            perft_cloning(&othello, othello.get_init_board(), 1, depth)
+        );
+    }
+}
+
+#[test]
+fn divide_symmetric_openings() {
+    // the four opening moves are symmetric, so their subtrees have equal size
+    for (n, depth) in [(4, 10), (6, 6), (8, 5)] {
+        let othello = Othello::new(n);
+        let mut board = *othello.get_init_board();
+        let counts = divide(&othello, &mut board, 1, depth);
+        assert_eq!(counts.len(), 4);
+        for (_, nodes) in &counts {
+            assert_eq!(*nodes, counts[0].1);
+        }
+        let total: u64 = counts.iter().map(|(_, nodes)| nodes).sum();
+        assert_eq!(total, perft(&othello, &mut board, 1, depth));
+    }
+}
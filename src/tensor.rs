@@ -0,0 +1,41 @@
+use burn::tensor::{backend::Backend, Data, Shape, Tensor};
+
+use crate::game::Game;
+
+/// Returns the boards as a tensor of shape [boards, x, y], where (x, y) is the
+/// board size of game and every board is filled in by `Game::encode`.
+pub fn boards<B: Backend, G: Game>(
+    game: &G,
+    boards: &[&G::State],
+    device: &B::Device,
+) -> Tensor<B, 3> {
+    let (x, y) = game.get_board_size();
+    let mut floats = Vec::with_capacity(boards.len() * x * y);
+    for board in boards {
+        floats.extend(game.encode(board));
+    }
+    let shape = Shape::new([boards.len(), x, y]);
+    let data = Data::<f32, 3>::new(floats, shape).convert();
+    Tensor::<B, 3>::from_data(data, device)
+}
+
+/// Returns the policy vectors as a tensor of shape [pis, action size].
+pub fn policies<B: Backend>(pis: &[&[f32]], device: &B::Device) -> Tensor<B, 2> {
+    let action_size = pis.first().map_or(0, |pi| pi.len());
+    let floats: Vec<f32> = pis.iter().flat_map(|pi| pi.iter().copied()).collect();
+    let shape = Shape::new([pis.len(), action_size]);
+    let data = Data::<f32, 2>::new(floats, shape).convert();
+    Tensor::<B, 2>::from_data(data, device)
+}
+
+/// Returns the values as a tensor of shape [vs].
+pub fn values<B: Backend>(vs: &[f32], device: &B::Device) -> Tensor<B, 1> {
+    let shape = Shape::new([vs.len()]);
+    let data = Data::<f32, 1>::new(vs.to_vec(), shape).convert();
+    Tensor::<B, 1>::from_data(data, device)
+}
+
+/// Returns the elements of tensor in row-major order.
+pub fn to_vec<B: Backend, const D: usize>(tensor: Tensor<B, D>) -> Vec<f32> {
+    tensor.to_data().convert::<f32>().value
+}
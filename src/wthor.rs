@@ -0,0 +1,260 @@
+use std::{fmt, fs, io, path::Path};
+
+use crate::{
+    board::Board,
+    game::Game,
+    game_record::{self, GameRecord, MoveRecord},
+    othello::{Move, Othello},
+};
+
+/// Size of the header of a WTHOR file.
+const HEADER_SIZE: usize = 16;
+/// Size of a game of a WTHOR file of 8x8 games: the tournament, the players,
+/// the scores and 60 moves.
+const GAME_SIZE: usize = 68;
+const SQUARES: i32 = 64;
+
+#[derive(Debug)]
+pub enum WthorError {
+    Io(io::Error),
+    /// The file is shorter than its header says.
+    Truncated {
+        expected: usize,
+        len: usize,
+    },
+    /// Only files of 8x8 games are supported.
+    BoardSize(u8),
+    /// A score is more than the number of squares.
+    InvalidScore(u8),
+    /// A move is not a square of the board.
+    InvalidMove {
+        ply: usize,
+        code: u8,
+    },
+    /// A move is not legal in the position it was played in.
+    IllegalMove {
+        ply: usize,
+        action: String,
+    },
+    /// The replayed game does not end with the recorded score.
+    ScoreMismatch {
+        recorded: u8,
+        replayed: u8,
+    },
+}
+
+impl fmt::Display for WthorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WthorError::Io(e) => write!(f, "{e}"),
+            WthorError::Truncated { expected, len } => {
+                write!(f, "the file has {len} bytes instead of {expected}")
+            }
+            WthorError::BoardSize(size) => write!(f, "board size {size} is not supported"),
+            WthorError::InvalidScore(score) => write!(f, "{score} is not a score"),
+            WthorError::InvalidMove { ply, code } => {
+                write!(f, "{code} at move {ply} is not a square")
+            }
+            WthorError::IllegalMove { ply, action } => {
+                write!(f, "{action} at move {ply} is not a legal move")
+            }
+            WthorError::ScoreMismatch { recorded, replayed } => write!(
+                f,
+                "the game was recorded with {recorded} discs for black but replays to {replayed}"
+            ),
+        }
+    }
+}
+
+/// The header of a WTHOR file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Header {
+    /// Date the file was written, as (year, month, day).
+    pub created: (u16, u8, u8),
+    /// Number of games in the file.
+    pub games: u32,
+    /// Year the games were played.
+    pub year: u16,
+    /// 8 for 8x8 games, or 0 in older files.
+    pub board_size: u8,
+    /// 0 for games, 1 for solitaires.
+    pub game_type: u8,
+    /// Number of empty squares from which the theoretical scores assume
+    /// perfect play.
+    pub depth: u8,
+}
+
+/// A game of a WTHOR file. Tournaments and players are numbers referring to
+/// the WTHOR.TRN and WTHOR.JOU files.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WthorGame {
+    pub tournament: u16,
+    pub black: u16,
+    pub white: u16,
+    /// Discs of black at the end of the game, empty squares counting for the
+    /// winner.
+    pub score: u8,
+    /// Discs of black with perfect play from the position with depth empty
+    /// squares.
+    pub theoretical_score: u8,
+    /// The squares played, 10 * row + column from 11 for a1 to 88 for h8.
+    /// Passes are left out.
+    pub moves: Vec<u8>,
+}
+
+impl WthorGame {
+    /// Replays the game through othello, which should be 8x8 Othello with
+    /// standard rules, adding the passes the file leaves out. A finished game
+    /// must end with the recorded score, and an unfinished one (lost on time,
+    /// or abandoned) is kept without result. Unlike the suffix of a GGF
+    /// result, WTHOR does not record why a game stopped, so there is no
+    /// Forfeit to give it, and a result from its score would be a guess.
+    pub fn to_record(&self, othello: &Othello) -> Result<GameRecord<Board>, WthorError> {
+        for score in [self.score, self.theoretical_score] {
+            if score as i32 > SQUARES {
+                return Err(WthorError::InvalidScore(score));
+            }
+        }
+        let mut board = *othello.get_init_board();
+        let mut player = 1;
+        let mut record = GameRecord::new(board, player)
+            .with_players(&format!("#{}", self.black), &format!("#{}", self.white));
+        for (ply, code) in self.moves.iter().enumerate() {
+            let (row, col) = ((code / 10) as usize, (code % 10) as usize);
+            if !(1..=8).contains(&row) || !(1..=8).contains(&col) {
+                return Err(WthorError::InvalidMove { ply, code: *code });
+            }
+            if !board.has_legal_moves(player) && othello.get_game_ended(&board, player).is_none() {
+                (board, player) = othello.get_next_state(&board, player, Move::Pass);
+                record
+                    .moves
+                    .push(MoveRecord::new(othello.action_to_string(Move::Pass)));
+            }
+            let action = Move::Place(row - 1, col - 1);
+            (board, player) = othello
+                .try_next_state(&board, player, action)
+                .map_err(|_| WthorError::IllegalMove {
+                    ply,
+                    action: othello.action_to_string(action),
+                })?;
+            record
+                .moves
+                .push(MoveRecord::new(othello.action_to_string(action)));
+        }
+        if let Some(result) = othello.get_game_ended(&board, player) {
+            let result = if player == 1 {
+                result
+            } else {
+                result.for_opponent()
+            };
+            let replayed = ((SQUARES + result.margin) / 2) as u8;
+            if replayed != self.score {
+                return Err(WthorError::ScoreMismatch {
+                    recorded: self.score,
+                    replayed,
+                });
+            }
+            record.result = Some(result);
+        }
+        Ok(record)
+    }
+}
+
+/// Parses the header and the games of a WTHOR .wtb file.
+pub fn parse(bytes: &[u8]) -> Result<(Header, Vec<WthorGame>), WthorError> {
+    if bytes.len() < HEADER_SIZE {
+        return Err(WthorError::Truncated {
+            expected: HEADER_SIZE,
+            len: bytes.len(),
+        });
+    }
+    let u16_at = |i: usize| u16::from_le_bytes([bytes[i], bytes[i + 1]]);
+    let header = Header {
+        created: (bytes[0] as u16 * 100 + bytes[1] as u16, bytes[2], bytes[3]),
+        games: u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+        year: u16_at(10),
+        board_size: bytes[12],
+        game_type: bytes[13],
+        depth: bytes[14],
+    };
+    if header.board_size != 0 && header.board_size != 8 {
+        return Err(WthorError::BoardSize(header.board_size));
+    }
+    let expected = HEADER_SIZE + header.games as usize * GAME_SIZE;
+    if bytes.len() < expected {
+        return Err(WthorError::Truncated {
+            expected,
+            len: bytes.len(),
+        });
+    }
+    let games = bytes[HEADER_SIZE..expected]
+        .chunks_exact(GAME_SIZE)
+        .map(|game| WthorGame {
+            tournament: u16::from_le_bytes([game[0], game[1]]),
+            black: u16::from_le_bytes([game[2], game[3]]),
+            white: u16::from_le_bytes([game[4], game[5]]),
+            score: game[6],
+            theoretical_score: game[7],
+            moves: game[8..].iter().copied().take_while(|m| *m != 0).collect(),
+        })
+        .collect();
+    Ok((header, games))
+}
+
+/// Replays games through 8x8 Othello.
+///
+/// Returns:
+///     records: the records of the valid games
+///     rejected: the index and error of every corrupt game
+pub fn to_records(games: &[WthorGame]) -> (Vec<GameRecord<Board>>, Vec<(usize, WthorError)>) {
+    let othello = Othello::new(8);
+    let mut records = Vec::new();
+    let mut rejected = Vec::new();
+    for (i, game) in games.iter().enumerate() {
+        match game.to_record(&othello) {
+            Ok(record) => records.push(record),
+            Err(e) => rejected.push((i, e)),
+        }
+    }
+    (records, rejected)
+}
+
+/// Reads the WTHOR file at path, see parse.
+pub fn load(path: &Path) -> Result<(Header, Vec<WthorGame>), WthorError> {
+    let bytes = fs::read(path).map_err(WthorError::Io)?;
+    parse(&bytes)
+}
+
+/// Runs `othello wthor <file> [output file] [examples]`, importing the games
+/// of a WTHOR file. They are saved to the output file as game records (see
+/// game_record::save_records), or as training examples that Coach loads with
+/// load_examples_file, with draws worth draw_value.
+pub fn command(args: &[String], draw_value: f32) {
+    let path = Path::new(
+        args.first()
+            .expect("Usage: othello wthor <file> [output file] [examples]"),
+    );
+    let (header, games) = match load(path) {
+        Ok(file) => file,
+        Err(e) => return println!("Could not read {}: {e}", path.display()),
+    };
+    let (records, rejected) = to_records(&games);
+    for (i, e) in &rejected {
+        println!("Rejected game {i}: {e}");
+    }
+    println!(
+        "{} games of {} ({} unfinished), {} rejected",
+        records.len(),
+        header.year,
+        records.iter().filter(|r| r.result.is_none()).count(),
+        rejected.len()
+    );
+    let Some(output) = args.get(1) else {
+        return;
+    };
+    let examples = args.get(2).is_some_and(|v| v == "examples");
+    game_record::save_imported(&Othello::new(8), &records, output, examples, draw_value);
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,195 @@
+use rand::Rng;
+
+use super::{parse, to_records, Header, WthorError, WthorGame, GAME_SIZE, HEADER_SIZE};
+use crate::{
+    board::Board,
+    game::Game,
+    othello::{Move, Othello},
+};
+
+/// Writes a WTHOR file of games played in year.
+fn wtb(year: u16, games: &[WthorGame]) -> Vec<u8> {
+    let mut bytes = vec![20, 24, 3, 17];
+    bytes.extend((games.len() as u32).to_le_bytes());
+    bytes.extend(0u16.to_le_bytes());
+    bytes.extend(year.to_le_bytes());
+    bytes.extend([8, 0, 22, 0]);
+    for game in games {
+        bytes.extend(game.tournament.to_le_bytes());
+        bytes.extend(game.black.to_le_bytes());
+        bytes.extend(game.white.to_le_bytes());
+        bytes.extend([game.score, game.theoretical_score]);
+        let mut moves = game.moves.clone();
+        moves.resize(60, 0);
+        bytes.extend(moves);
+    }
+    bytes
+}
+
+/// A random 8x8 game as WTHOR stores it, with the number of passes played.
+fn random_game(rng: &mut impl Rng) -> (WthorGame, usize) {
+    let othello = Othello::new(8);
+    let mut board = *othello.get_init_board();
+    let mut player = 1;
+    let mut moves = Vec::new();
+    let mut passes = 0;
+    while othello.get_game_ended(&board, player).is_none() {
+        let valids = othello.get_valid_moves(&board, player);
+        let legal: Vec<usize> = (0..valids.len()).filter(|a| valids[*a] == 1).collect();
+        let action = othello.index_to_action(legal[rng.gen_range(0..legal.len())]);
+        match action {
+            Move::Place(x, y) => moves.push((10 * (x + 1) + y + 1) as u8),
+            Move::Pass => passes += 1,
+        }
+        (board, player) = othello.get_next_state(&board, player, action);
+    }
+    let margin = othello.get_game_ended(&board, 1).unwrap().margin;
+    let game = WthorGame {
+        tournament: 1,
+        black: 2,
+        white: 3,
+        score: ((64 + margin) / 2) as u8,
+        theoretical_score: 32,
+        moves,
+    };
+    (game, passes)
+}
+
+#[test]
+fn parse_header_and_games() {
+    let game = WthorGame {
+        tournament: 513,
+        black: 1000,
+        white: 7,
+        score: 40,
+        theoretical_score: 34,
+        moves: vec![56, 64, 33],
+    };
+    let bytes = wtb(1999, &[game.clone(), game.clone()]);
+    assert_eq!(bytes.len(), HEADER_SIZE + 2 * GAME_SIZE);
+    let (header, games) = parse(&bytes).unwrap();
+    assert_eq!(
+        header,
+        Header {
+            created: (2024, 3, 17),
+            games: 2,
+            year: 1999,
+            board_size: 8,
+            game_type: 0,
+            depth: 22,
+        }
+    );
+    assert_eq!(games, vec![game.clone(), game]);
+}
+
+#[test]
+fn parse_rejects_truncated_and_other_sizes() {
+    let mut bytes = wtb(2000, &[random_game(&mut rand::thread_rng()).0]);
+    bytes.pop();
+    assert!(matches!(
+        parse(&bytes),
+        Err(WthorError::Truncated {
+            expected: 84,
+            len: 83
+        })
+    ));
+    assert!(matches!(
+        parse(&bytes[..10]),
+        Err(WthorError::Truncated { .. })
+    ));
+    bytes[12] = 10;
+    assert!(matches!(parse(&bytes), Err(WthorError::BoardSize(10))));
+}
+
+#[test]
+fn random_games_replay_with_passes() {
+    let mut rng = rand::thread_rng();
+    let games: Vec<(WthorGame, usize)> = (0..20).map(|_| random_game(&mut rng)).collect();
+    let bytes = wtb(
+        2001,
+        &games.iter().map(|(g, _)| g.clone()).collect::<Vec<_>>(),
+    );
+    let (_, parsed) = parse(&bytes).unwrap();
+    let (records, rejected) = to_records(&parsed);
+    assert!(rejected.is_empty());
+    let othello = Othello::new(8);
+    for (record, (game, passes)) in records.iter().zip(&games) {
+        assert_eq!(record.players, ["#2".to_owned(), "#3".to_owned()]);
+        assert_eq!(record.moves.len(), game.moves.len() + passes);
+        assert_eq!(
+            record.moves.iter().filter(|m| m.action == "pass").count(),
+            *passes
+        );
+        assert!(record.result.is_some());
+        assert!(record.replay(&othello).is_ok());
+    }
+}
+
+#[test]
+fn corrupt_games_are_rejected() {
+    let othello = Othello::new(8);
+    // f5 d6 c3
+    let game = WthorGame {
+        tournament: 0,
+        black: 0,
+        white: 0,
+        score: 33,
+        theoretical_score: 33,
+        moves: vec![56, 64, 33],
+    };
+    let record = game.to_record(&othello).unwrap();
+    // an unfinished game has no result
+    assert_eq!(record.result, None);
+    assert_eq!(record.moves.len(), 3);
+    assert_eq!(record.moves[1].action, "d6");
+
+    let mut illegal = game.clone();
+    illegal.moves[1] = 11;
+    assert!(matches!(
+        illegal.to_record(&othello),
+        Err(WthorError::IllegalMove { ply: 1, .. })
+    ));
+    let mut off_board = game.clone();
+    off_board.moves[2] = 39;
+    assert!(matches!(
+        off_board.to_record(&othello),
+        Err(WthorError::InvalidMove { ply: 2, code: 39 })
+    ));
+    let mut invalid_score = game;
+    invalid_score.theoretical_score = 65;
+    assert!(matches!(
+        invalid_score.to_record(&othello),
+        Err(WthorError::InvalidScore(65))
+    ));
+
+    let (mut finished, _) = random_game(&mut rand::thread_rng());
+    finished.score = if finished.score == 64 { 0 } else { 64 };
+    assert!(matches!(
+        finished.to_record(&othello),
+        Err(WthorError::ScoreMismatch { .. })
+    ));
+    let (records, rejected) = to_records(&[finished, illegal]);
+    assert!(records.is_empty());
+    assert_eq!(
+        rejected.iter().map(|(i, _)| *i).collect::<Vec<usize>>(),
+        vec![0, 1]
+    );
+}
+
+#[test]
+fn records_give_training_examples() {
+    let othello = Othello::new(8);
+    let (game, passes) = random_game(&mut rand::thread_rng());
+    let record = game.to_record(&othello).unwrap();
+    let examples: Vec<(Board, Vec<f32>, f32)> = record.training_examples(&othello, 0.).unwrap();
+    // eight symmetries of every position a move was played in
+    assert_eq!(examples.len(), 8 * (game.moves.len() + passes));
+    let (board, pi, value) = &examples[0];
+    assert_eq!(board, othello.get_init_board());
+    let first = game.moves[0] as usize;
+    let action = Move::Place(first / 10 - 1, first % 10 - 1);
+    assert_eq!(pi.iter().sum::<f32>(), 1.);
+    assert_eq!(pi[othello.action_to_index(action)], 1.);
+    let black = record.result.unwrap().value(0.);
+    assert_eq!(*value, black);
+}
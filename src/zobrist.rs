@@ -0,0 +1,90 @@
+use std::{
+    collections::HashMap,
+    hash::{BuildHasherDefault, Hasher},
+};
+
+/// Zobrist keys for a board with two colors. Sets of squares can be hashed
+/// at once for boards of up to 64 squares.
+///
+/// The key of a disc of player -1 is the key of player 1 rotated by 32 bits.
+/// Rotation distributes over xor, so swapping the colors of every disc on a
+/// board turns its hash `h` into `h.rotate_left(32)`. This makes the hash of
+/// the canonical form available without rehashing the board.
+#[derive(Clone)]
+pub struct Zobrist {
+    keys: Vec<u64>,
+}
+
+impl Zobrist {
+    const SEED: u64 = 0x4f74_6865_6c6c_6f21;
+
+    pub fn new(squares: usize) -> Self {
+        // splitmix64 with a fixed seed keeps hashes stable between runs
+        let mut state = Self::SEED;
+        let mut keys = Vec::with_capacity(squares);
+        for _ in 0..squares {
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            keys.push(z ^ (z >> 31));
+        }
+        Zobrist { keys }
+    }
+
+    /// Returns the xor of the keys of color for every bit set in squares.
+    pub fn hash_bits(&self, mut squares: u64, color: i8) -> u64 {
+        let mut hash = 0;
+        while squares != 0 {
+            hash ^= self.keys[squares.trailing_zeros() as usize];
+            squares &= squares - 1;
+        }
+        Self::for_color(hash, color)
+    }
+
+    /// Returns the key of color for a single square.
+    pub fn hash_square(&self, square: usize, color: i8) -> u64 {
+        Self::for_color(self.keys[square], color)
+    }
+
+    /// Returns the hash of the board with the colors of all discs swapped
+    /// when player is -1, and the hash itself otherwise.
+    pub fn canonical(hash: u64, player: i8) -> u64 {
+        Self::for_color(hash, player)
+    }
+
+    fn for_color(hash: u64, color: i8) -> u64 {
+        if color == 1 {
+            hash
+        } else {
+            hash.rotate_left(32)
+        }
+    }
+}
+
+/// Hasher for maps keyed by Zobrist hashes, which are random already and
+/// only need to be mixed cheaply.
+#[derive(Default)]
+pub struct ZobristHasher(u64);
+
+impl Hasher for ZobristHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.write_u64(*b as u64);
+        }
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.0 = (self.0.rotate_left(5) ^ i).wrapping_mul(0x517c_c1b7_2722_0a95);
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+}
+
+pub type ZobristMap<K, V> = HashMap<K, V, BuildHasherDefault<ZobristHasher>>;